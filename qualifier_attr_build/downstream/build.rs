@@ -0,0 +1,3 @@
+fn main() {
+    qualifier_attr_build::emit_cfgs();
+}
@@ -0,0 +1,3 @@
+//! Uses `qualifier_attr_build` in its build script like a downstream crate, so that its tests can
+//! check the version-gated qualifiers and the headers written for
+//! its features.
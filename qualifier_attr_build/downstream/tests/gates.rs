@@ -0,0 +1,41 @@
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+// The build script emits the cfgs, so gates on old versions hold...
+#[qualifiers(const(since = "1.56"))]
+fn answer() -> u32 {
+    42
+}
+
+const ANSWER: u32 = answer();
+
+// ...while gates on versions from the future don't.
+#[qualifiers(const(since = "1.200"))]
+fn future_answer() -> u32 {
+    42
+}
+
+#[qualifiers(pub, const(nightly))]
+fn nightly_answer() -> u32 {
+    42
+}
+
+// On nightly, the function is `const`.
+#[cfg(qualifier_attr_nightly)]
+const NIGHTLY_ANSWER: u32 = nightly_answer();
+
+#[cfg(not(all(
+    qualifier_attr_build,
+    qualifier_attr_since_1_56,
+    not(qualifier_attr_since_1_200)
+)))]
+compile_error!("the build script emits the cfgs of the compiler");
+
+#[test]
+fn test_gates() {
+    assert_eq!(ANSWER, 42);
+    assert_eq!(future_answer(), 42);
+    assert_eq!(nightly_answer(), 42);
+}
@@ -0,0 +1,19 @@
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+// The build script records the features by their real names, so only the first function is
+// written to the header.
+#[qualifiers(export(prefix = "downstream_"), extern "C"(header = "downstream.h") if feature = "my-feature")]
+fn dashed() {}
+
+#[qualifiers(export(prefix = "downstream_"), extern "C"(header = "downstream.h") if feature = "my_feature" else extern "C")]
+fn underscored() {}
+
+#[test]
+fn test_header() {
+    let header = std::fs::read_to_string(concat!(env!("OUT_DIR"), "/downstream.h")).unwrap();
+    assert!(header.contains("void downstream_dashed(void);\n"));
+    assert!(!header.contains("downstream_underscored"));
+}
@@ -0,0 +1,182 @@
+//! Build script helper for the version-gated qualifiers of [`qualifier_attr`], such as
+//! `#[qualifiers(const(since = "1.61"))]` and `#[qualifiers(const(nightly))]`.
+//!
+//! The qualifiers expand to `cfg`-gated items, and this crate probes the compiler and emits the
+//! cfgs they check. Call [`emit_cfgs`] from the build script of the crate that uses them:
+//!
+//! ```no_run
+//! // In the `main` function of `build.rs`:
+//! qualifier_attr_build::emit_cfgs();
+//! ```
+//!
+//! Without it, the gated qualifiers report that the build script is missing.
+//!
+//! It also records the cfgs that the crate is built with, so that
+//! `extern "C"(header = "...")` with conditional qualifiers only writes the functions that are
+//! built to the header, and clears the functions that earlier builds wrote to headers.
+//!
+//! [`qualifier_attr`]: https://docs.rs/qualifier_attr
+
+use std::{env, fs, io, path::Path, process::Command};
+
+/// The file under `OUT_DIR` that the cfgs of the crate are recorded in, one per line, where
+/// `qualifier_attr` looks them up.
+const CFGS_FILE: &str = "qualifier_attr_cfgs";
+
+/// The directory under `OUT_DIR` that `qualifier_attr` writes the C prototypes of functions to,
+/// one directory per header.
+const PROTOTYPES_DIR: &str = "qualifier_attr_prototypes";
+
+/// The cfg that tells `qualifier_attr` that this crate is used by the build script.
+const MARKER_CFG: &str = "qualifier_attr_build";
+
+/// The highest minor version declared to `check-cfg`, so that gates on future versions don't
+/// trigger the `unexpected_cfgs` lint.
+const MAX_MINOR_VERSION: u32 = 200;
+
+/// The version of a Rust compiler.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rustc {
+    /// The minor version, e.g. `61` for Rust 1.61.
+    pub minor: u32,
+    /// Whether the compiler is a nightly or dev build.
+    pub nightly: bool,
+}
+
+impl Rustc {
+    /// Probes the compiler that cargo builds the crate with.
+    pub fn probe() -> Option<Self> {
+        let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let output = Command::new(rustc).arg("-vV").output().ok()?;
+        Self::parse(std::str::from_utf8(&output.stdout).ok()?)
+    }
+
+    /// Parses the output of `rustc -vV`.
+    pub fn parse(verbose_version: &str) -> Option<Self> {
+        let release = verbose_version
+            .lines()
+            .find_map(|line| line.strip_prefix("release: "))?;
+        let (version, channel) = match release.split_once('-') {
+            Some((version, channel)) => (version, Some(channel)),
+            None => (release, None),
+        };
+
+        let mut parts = version.split('.');
+        if parts.next()? != "1" {
+            return None;
+        }
+        let minor = parts.next()?.parse().ok()?;
+        let nightly =
+            matches!(channel, Some(channel) if channel.starts_with("nightly") || channel == "dev");
+        Some(Self { minor, nightly })
+    }
+
+    /// Returns the cfgs that hold for this compiler.
+    pub fn cfgs(&self) -> Vec<String> {
+        let mut cfgs: Vec<String> = (0..=self.minor)
+            .map(|minor| format!("qualifier_attr_since_1_{}", minor))
+            .collect();
+        if self.nightly {
+            cfgs.push("qualifier_attr_nightly".to_owned());
+        }
+        cfgs
+    }
+}
+
+/// Probes the compiler and emits the cfgs that hold for it, along with `check-cfg` declarations
+/// for every cfg that the version-gated qualifiers may check.
+///
+/// It also emits the `qualifier_attr_build` cfg, without which the gated qualifiers report that
+/// the build script is missing. If the compiler can't be probed, no version cfgs are emitted and
+/// the gated qualifiers aren't applied.
+///
+/// The headers written by `extern "C"(header = "...")` are removed along with the prototypes
+/// that they were put together from, so that functions that were renamed or removed since the
+/// last build are left out once the crate is compiled again.
+pub fn emit_cfgs() {
+    let mut names: Vec<String> = (0..=MAX_MINOR_VERSION)
+        .map(|minor| format!("qualifier_attr_since_1_{}", minor))
+        .collect();
+    names.push("qualifier_attr_nightly".to_owned());
+    names.push(MARKER_CFG.to_owned());
+    println!("cargo:rustc-check-cfg=cfg({})", names.join(", "));
+    println!("cargo:rustc-cfg={}", MARKER_CFG);
+
+    let cfgs = Rustc::probe().map_or_else(Vec::new, |rustc| rustc.cfgs());
+    for cfg in &cfgs {
+        println!("cargo:rustc-cfg={}", cfg);
+    }
+    record_cfgs(cfgs);
+    clear_headers();
+}
+
+/// Records the cfgs that the crate is built with, i.e. its features, the cfgs of the target, and
+/// the version cfgs, to `OUT_DIR`.
+///
+/// Build scripts don't see `test` or `doc`, nor cfgs emitted by other build scripts, so those
+/// aren't recorded.
+fn record_cfgs(mut cfgs: Vec<String>) {
+    let out_dir = match env::var_os("OUT_DIR") {
+        Some(out_dir) => out_dir,
+        None => return,
+    };
+    // Cargo tells the real names of features in `CARGO_CFG_FEATURE` since 1.85, and only in
+    // upper case with `_` for `-` before, which `qualifier_attr` records apart.
+    let real_features = env::var_os("CARGO_CFG_FEATURE").is_some();
+    for (key, value) in env::vars() {
+        if let Some(feature) = key.strip_prefix("CARGO_FEATURE_") {
+            if !real_features {
+                cfgs.push(format!("qualifier_attr_feature={}", feature));
+            }
+        } else if let Some(name) = key.strip_prefix("CARGO_CFG_") {
+            let name = name.to_lowercase();
+            if value.is_empty() {
+                cfgs.push(name);
+            } else {
+                cfgs.extend(
+                    value
+                        .split(',')
+                        .map(|value| format!("{}=\"{}\"", name, value)),
+                );
+            }
+        }
+    }
+    cfgs.sort();
+
+    let path = Path::new(&out_dir).join(CFGS_FILE);
+    if fs::read_to_string(&path).ok().as_deref() != Some(&*cfgs.join("\n")) {
+        if let Err(error) = fs::write(&path, cfgs.join("\n")) {
+            println!(
+                "cargo:warning=failed to record the cfgs of the crate for `qualifier_attr`: {}",
+                error
+            );
+        }
+    }
+}
+
+/// Removes the headers in `OUT_DIR` and the prototypes they were put together from.
+///
+/// The build script runs before the crate is compiled, and not during, so the compilations that
+/// write to the headers never see them half-removed.
+fn clear_headers() {
+    let out_dir = match env::var_os("OUT_DIR") {
+        Some(out_dir) => out_dir,
+        None => return,
+    };
+    let prototypes = Path::new(&out_dir).join(PROTOTYPES_DIR);
+    let headers = match fs::read_dir(&prototypes) {
+        Ok(headers) => headers,
+        Err(_) => return,
+    };
+    let result = headers
+        .map(|header| fs::remove_file(Path::new(&out_dir).join(header?.file_name())))
+        .filter(|result| !matches!(result, Err(error) if error.kind() == io::ErrorKind::NotFound))
+        .collect::<io::Result<()>>()
+        .and_then(|()| fs::remove_dir_all(&prototypes));
+    if let Err(error) = result {
+        println!(
+            "cargo:warning=failed to clear the headers written by `qualifier_attr`: {}",
+            error
+        );
+    }
+}
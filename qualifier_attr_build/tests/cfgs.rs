@@ -0,0 +1,37 @@
+use qualifier_attr_build::Rustc;
+
+// The version-gated qualifiers themselves are tested by the `downstream` crate, which uses this
+// one in its build script.
+#[test]
+fn test_cfgs() {
+    let stable = Rustc::parse(
+        "rustc 1.61.0 (fe5b13d68 2022-05-18)\nbinary: rustc\nhost: x86_64-unknown-linux-gnu\nrelease: 1.61.0\nLLVM version: 14.0.0\n",
+    );
+    assert_eq!(
+        stable,
+        Some(Rustc {
+            minor: 61,
+            nightly: false
+        })
+    );
+    let cfgs = stable.unwrap().cfgs();
+    assert!(cfgs.contains(&"qualifier_attr_since_1_61".to_owned()));
+    assert!(!cfgs.contains(&"qualifier_attr_since_1_62".to_owned()));
+    assert!(!cfgs.contains(&"qualifier_attr_nightly".to_owned()));
+
+    let nightly =
+        Rustc::parse("rustc 1.80.0-nightly (7c52d2db6 2024-06-03)\nrelease: 1.80.0-nightly\n");
+    assert_eq!(
+        nightly,
+        Some(Rustc {
+            minor: 80,
+            nightly: true
+        })
+    );
+    assert!(nightly
+        .unwrap()
+        .cfgs()
+        .contains(&"qualifier_attr_nightly".to_owned()));
+
+    assert_eq!(Rustc::parse("rustc 1.61.0"), None);
+}
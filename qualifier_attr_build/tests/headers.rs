@@ -0,0 +1,31 @@
+use std::fs;
+
+// `emit_cfgs` clears the headers that `qualifier_attr` wrote in earlier builds, which the crate
+// writes again when it is compiled, without the functions that were renamed or removed since.
+#[test]
+fn test_clear_headers() {
+    let out_dir = std::env::temp_dir().join(format!(
+        "qualifier_attr_build_headers_{}",
+        std::process::id()
+    ));
+    let prototypes = out_dir.join("qualifier_attr_prototypes").join("plugin.h");
+    fs::create_dir_all(&prototypes).unwrap();
+    fs::write(
+        prototypes.join("plugin_removed.h"),
+        "void plugin_removed(void);\n",
+    )
+    .unwrap();
+    fs::write(out_dir.join("plugin.h"), "void plugin_removed(void);\n").unwrap();
+    fs::write(out_dir.join("other.h"), "").unwrap();
+    std::env::set_var("OUT_DIR", &out_dir);
+
+    qualifier_attr_build::emit_cfgs();
+
+    assert!(!out_dir.join("qualifier_attr_prototypes").exists());
+    assert!(!out_dir.join("plugin.h").exists());
+    // Other files are left alone.
+    assert!(out_dir.join("other.h").exists());
+    assert!(out_dir.join("qualifier_attr_cfgs").exists());
+
+    fs::remove_dir_all(&out_dir).unwrap();
+}
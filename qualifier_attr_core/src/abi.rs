@@ -0,0 +1,120 @@
+use syn::Abi;
+
+/// The ABIs that stable Rust supports.
+const STABLE_ABIS: &[&str] = &[
+    "C",
+    "C-unwind",
+    "Rust",
+    "aapcs",
+    "aapcs-unwind",
+    "cdecl",
+    "cdecl-unwind",
+    "efiapi",
+    "fastcall",
+    "fastcall-unwind",
+    "stdcall",
+    "stdcall-unwind",
+    "system",
+    "system-unwind",
+    "sysv64",
+    "sysv64-unwind",
+    "thiscall",
+    "thiscall-unwind",
+    "win64",
+    "win64-unwind",
+];
+
+/// The ABIs that only nightly Rust supports, along with the features that enable them.
+const NIGHTLY_ABIS: &[(&str, &str)] = &[
+    ("C-cmse-nonsecure-call", "abi_c_cmse_nonsecure_call"),
+    ("C-cmse-nonsecure-entry", "cmse_nonsecure_entry"),
+    ("avr-interrupt", "abi_avr_interrupt"),
+    ("avr-non-blocking-interrupt", "abi_avr_interrupt"),
+    ("gpu-kernel", "abi_gpu_kernel"),
+    ("msp430-interrupt", "abi_msp430_interrupt"),
+    ("ptx-kernel", "abi_ptx"),
+    ("riscv-interrupt-m", "abi_riscv_interrupt"),
+    ("riscv-interrupt-s", "abi_riscv_interrupt"),
+    ("rust-call", "unboxed_closures"),
+    ("rust-cold", "rust_cold_cc"),
+    ("rust-intrinsic", "intrinsics"),
+    ("unadjusted", "abi_unadjusted"),
+    ("vectorcall", "abi_vectorcall"),
+    ("vectorcall-unwind", "abi_vectorcall"),
+    ("wasm", "wasm_abi"),
+    ("x86-interrupt", "abi_x86_interrupt"),
+];
+
+/// Checks that the ABI is one that rustc knows, and that it doesn't need a nightly feature.
+///
+/// Both can be skipped with `unchecked_abi`, e.g. for crates that enable the feature.
+pub(crate) fn check_abi(abi: &Abi) -> syn::Result<()> {
+    let name = match &abi.name {
+        Some(name) => name,
+        // `extern` on its own is `extern "C"`.
+        None => return Ok(()),
+    };
+    let value = name.value();
+
+    if STABLE_ABIS.contains(&&*value) {
+        return Ok(());
+    }
+    if let Some((_, feature)) = NIGHTLY_ABIS.iter().find(|(abi, _)| *abi == value) {
+        return Err(syn::Error::new(
+            name.span(),
+            format!(
+                "the `{}` ABI is unstable and requires `#![feature({})]`; add `unchecked_abi` in crates that enable it",
+                value, feature
+            ),
+        ));
+    }
+
+    let message = match suggest(&value) {
+        Some(suggestion) => format!(
+            "`{}` is not an ABI that rustc knows; did you mean `{}`?",
+            value, suggestion
+        ),
+        None => format!(
+            "`{}` is not an ABI that rustc knows; add `unchecked_abi` if it is new",
+            value
+        ),
+    };
+    Err(syn::Error::new(name.span(), message))
+}
+
+/// Returns the known ABI that is closest to the given one, if any is close enough.
+fn suggest(value: &str) -> Option<&'static str> {
+    let lowercase = value.to_lowercase();
+    STABLE_ABIS
+        .iter()
+        .chain(NIGHTLY_ABIS.iter().map(|(abi, _)| abi))
+        .map(|abi| {
+            // Differences in case alone, like in `extern "c"`, are the most likely typos.
+            let distance = if abi.to_lowercase() == lowercase {
+                0
+            } else {
+                levenshtein(abi, value)
+            };
+            (*abi, distance)
+        })
+        .filter(|(abi, distance)| *distance <= (abi.len() / 3).max(1))
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(abi, _)| abi)
+}
+
+/// Returns the number of single character insertions, deletions, and substitutions that turn one
+/// string into the other.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
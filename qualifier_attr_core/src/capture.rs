@@ -0,0 +1,116 @@
+use pm2::{Span, TokenStream};
+use quote::quote_spanned;
+use syn::{
+    parse_quote_spanned,
+    punctuated::Punctuated,
+    visit_mut::{self, VisitMut},
+    FnArg, Generics, Ident, Lifetime, ParenthesizedGenericArguments, Receiver, Token, Type,
+    TypeBareFn, TypeReference,
+};
+
+/// Makes the future returned by a desugared `async` function capture the lifetimes of its
+/// parameters, and returns the bound to add to its `impl Future`.
+///
+/// In edition 2021, `impl Trait` outside traits only captures the lifetimes named in its bounds.
+/// The elided lifetimes of the parameters are named, and the type of every parameter is bounded
+/// by a new lifetime that the future outlives, as `async fn` does implicitly. Returns `None` if
+/// the parameters have no lifetimes to capture.
+///
+/// Lifetimes bounded in the `where` clause are early-bound, so the function no longer coerces to
+/// a higher-ranked function pointer. Edition 2021 has no other way of capturing them short of
+/// `use<..>`, which needs Rust 1.82, or a helper trait the signature could name.
+pub(crate) fn capture_lifetimes(
+    generics: &mut Generics,
+    inputs: &mut Punctuated<FnArg, Token![,]>,
+    span: Span,
+) -> Option<TokenStream> {
+    let mut namer = LifetimeNamer {
+        span,
+        named: Vec::new(),
+        captures: false,
+    };
+    for input in inputs.iter_mut() {
+        namer.visit_fn_arg_mut(input);
+    }
+    if !namer.captures {
+        return None;
+    }
+
+    let future = Lifetime::new("'__future", span);
+    for lifetime in namer.named.iter().rev() {
+        generics
+            .params
+            .insert(0, parse_quote_spanned!(span=> #lifetime));
+    }
+    generics
+        .params
+        .insert(0, parse_quote_spanned!(span=> #future));
+    let where_clause = generics.make_where_clause();
+    for input in inputs.iter() {
+        let ty = match input {
+            FnArg::Receiver(receiver) => &receiver.ty,
+            FnArg::Typed(pat_type) => &pat_type.ty,
+        };
+        where_clause
+            .predicates
+            .push(parse_quote_spanned!(span=> #ty: #future));
+    }
+    Some(quote_spanned!(span=> + #future))
+}
+
+/// Names the elided lifetimes of parameters and tracks whether they have any lifetimes.
+struct LifetimeNamer {
+    span: Span,
+    named: Vec<Lifetime>,
+    /// Whether a lifetime or `Self`, which may have lifetimes, was found.
+    captures: bool,
+}
+
+impl LifetimeNamer {
+    fn name(&mut self) -> Lifetime {
+        let lifetime = Lifetime::new(&format!("'__life{}", self.named.len()), self.span);
+        self.named.push(lifetime.clone());
+        self.captures = true;
+        lifetime
+    }
+}
+
+impl VisitMut for LifetimeNamer {
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = self.name();
+        } else if lifetime.ident != "static" {
+            self.captures = true;
+        }
+    }
+
+    fn visit_type_reference_mut(&mut self, type_reference: &mut TypeReference) {
+        if type_reference.lifetime.is_none() {
+            type_reference.lifetime = Some(self.name());
+        }
+        visit_mut::visit_type_reference_mut(self, type_reference);
+    }
+
+    fn visit_receiver_mut(&mut self, receiver: &mut Receiver) {
+        self.captures = true;
+        visit_mut::visit_type_mut(self, &mut receiver.ty);
+        // The shorthand of `&self` is printed from its reference rather than its type.
+        if let (Some((_, lifetime)), Type::Reference(ty)) = (&mut receiver.reference, &*receiver.ty)
+        {
+            *lifetime = ty.lifetime.clone();
+        }
+    }
+
+    fn visit_ident_mut(&mut self, ident: &mut Ident) {
+        if ident == "Self" {
+            self.captures = true;
+        }
+    }
+
+    // The elided lifetimes of function pointers and `Fn` bounds are higher-ranked.
+
+    fn visit_type_bare_fn_mut(&mut self, _: &mut TypeBareFn) {}
+
+    fn visit_parenthesized_generic_arguments_mut(&mut self, _: &mut ParenthesizedGenericArguments) {
+    }
+}
@@ -0,0 +1,176 @@
+use quote::{quote, ToTokens};
+#[cfg(feature = "full")]
+use syn::{parse_quote, Attribute};
+use syn::{spanned::Spanned, Meta};
+
+use crate::parse::is_version_gate;
+
+/// The maximum number of distinct predicates, since every combination of them gets its own
+/// copy of the item or element.
+const MAX_PREDICATES: usize = 6;
+
+/// A combination of `cfg` predicates, i.e. which of them hold.
+pub(crate) struct Combination<'a> {
+    predicates: &'a [Meta],
+    mask: u32,
+}
+
+impl Combination<'_> {
+    /// Returns whether the predicate holds in this combination. Predicates that aren't part of
+    /// it never hold.
+    pub fn holds(&self, predicate: &Meta) -> bool {
+        let predicate_string = predicate.to_token_stream().to_string();
+        self.predicates
+            .iter()
+            .position(|other| other.to_token_stream().to_string() == predicate_string)
+            .map_or(false, |i| self.mask & 1 << i != 0)
+    }
+
+    /// Returns the predicate that holds exactly in this combination, e.g. `all(unix, not(test))`.
+    pub fn gate(&self) -> pm2::TokenStream {
+        let gates = self.predicates.iter().enumerate().map(|(i, predicate)| {
+            if self.mask & 1 << i != 0 {
+                quote!(#predicate)
+            } else {
+                quote!(not(#predicate))
+            }
+        });
+        quote!(all(#(#gates),*))
+    }
+}
+
+/// Returns every combination of the predicates, or an error if there are too many of them.
+fn combinations(predicates: &[Meta]) -> syn::Result<Vec<Combination<'_>>> {
+    if let Some(predicate) = predicates.get(MAX_PREDICATES) {
+        return Err(syn::Error::new(
+            predicate.span(),
+            format!(
+                "too many distinct `cfg` predicates; at most {} are supported",
+                MAX_PREDICATES
+            ),
+        ));
+    }
+    Ok((0..1u32 << predicates.len())
+        .map(|mask| Combination { predicates, mask })
+        .collect())
+}
+
+/// Expands an item into `cfg`-gated copies, one for every combination of the predicates that
+/// conditional qualifiers depend on.
+///
+/// `qualify` is called once per combination with a function telling which predicates hold,
+/// and returns the qualified item along with the items emitted next to it, each of which is
+/// gated. Errors are only reported for the combinations that are built, along with the
+/// `fallback` item. Without predicates, the item is qualified once and not gated at all.
+///
+/// Copying the item is the only way to gate its qualifiers, since `cfg_attr` can only add
+/// attributes, not keywords or visibilities. Only the qualifiers of the item itself need it,
+/// though: fields and members are expanded one by one with [`expand_element`] instead, so that
+/// their conditions don't multiply the copies of the item.
+pub(crate) fn expand(
+    predicates: &[Meta],
+    fallback: &pm2::TokenStream,
+    mut qualify: impl FnMut(&dyn Fn(&Meta) -> bool) -> syn::Result<Vec<pm2::TokenStream>>,
+) -> syn::Result<pm2::TokenStream> {
+    if predicates.is_empty() {
+        return Ok(qualify(&|_| false)?.into_iter().collect());
+    }
+
+    let mut output = pm2::TokenStream::new();
+    for combination in combinations(predicates)? {
+        let gate = combination.gate();
+        match qualify(&|predicate| combination.holds(predicate)) {
+            Ok(items) => {
+                for item in items {
+                    output.extend(quote! {
+                        #[cfg(#gate)]
+                        #item
+                    });
+                }
+            }
+            // Errors are gated as well, since the qualifiers of a combination may well be
+            // invalid if it is never built, e.g. `const` when the body isn't const yet.
+            Err(error) => {
+                for error in error {
+                    let error = error.into_compile_error();
+                    output.extend(quote! {
+                        #[cfg(#gate)]
+                        #error
+                    });
+                }
+                output.extend(quote! {
+                    #[cfg(#gate)]
+                    #fallback
+                });
+            }
+        }
+    }
+
+    output.extend(check_build_script(predicates));
+    Ok(output)
+}
+
+/// Expands an element of an item, i.e. a field or a member, into `cfg`-gated copies, one for
+/// every combination of the predicates that its own qualifiers depend on, so that elements with
+/// different conditions don't multiply the copies of the whole item.
+///
+/// `qualify` is called once per combination with a copy of the element, which is then gated with
+/// `gate`. Errors are gated in the same way and added to `errors`, to be emitted next to the
+/// item, and the element is left unqualified in their combinations. Without predicates, the
+/// element is qualified once and not gated at all.
+#[cfg(feature = "full")]
+pub(crate) fn expand_element<T: Clone>(
+    element: &T,
+    predicates: &[Meta],
+    gate: fn(&mut T, Attribute),
+    mut qualify: impl FnMut(&mut T, &Combination) -> syn::Result<()>,
+    errors: &mut pm2::TokenStream,
+) -> syn::Result<Vec<T>> {
+    let mut copies = Vec::new();
+    for combination in combinations(predicates)? {
+        let mut copy = element.clone();
+        let gate_attr = match predicates.is_empty() {
+            true => None,
+            false => {
+                let predicate = combination.gate();
+                Some(parse_quote!(#[cfg(#predicate)]))
+            }
+        };
+        if let Err(error) = qualify(&mut copy, &combination) {
+            copy = element.clone();
+            for error in error {
+                let error = error.into_compile_error();
+                errors.extend(quote! {
+                    #gate_attr
+                    #error
+                });
+            }
+        }
+        if let Some(gate_attr) = gate_attr {
+            gate(&mut copy, gate_attr);
+        }
+        copies.push(copy);
+    }
+    errors.extend(check_build_script(predicates));
+    Ok(copies)
+}
+
+/// Reports version gates among the predicates when the build script doesn't emit the cfgs they
+/// check.
+///
+/// Without the build script, the version cfgs never hold, so the gated qualifiers would
+/// silently never apply.
+fn check_build_script(predicates: &[Meta]) -> Option<pm2::TokenStream> {
+    let gate = predicates
+        .iter()
+        .find(|predicate| is_version_gate(predicate))?;
+    let error = syn::Error::new(
+        gate.span(),
+        "`const(since = \"...\")` and `const(nightly)` need `qualifier_attr_build::emit_cfgs()` in the build script, which emits the cfgs they check",
+    )
+    .into_compile_error();
+    Some(quote! {
+        #[cfg(not(qualifier_attr_build))]
+        #error
+    })
+}
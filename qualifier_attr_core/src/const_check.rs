@@ -0,0 +1,126 @@
+use syn::{
+    spanned::Spanned,
+    visit::{self, Visit},
+    Block, Expr, ExprPath, Item, Macro, Token,
+};
+
+/// Traits of the standard library, whose functions can't be called in constant functions when
+/// named through the trait, e.g. `Default::default()` or `Iterator::next(&mut iter)`.
+const TRAITS: &[&str] = &[
+    "AsMut",
+    "AsRef",
+    "Clone",
+    "Default",
+    "Display",
+    "Debug",
+    "From",
+    "Into",
+    "IntoIterator",
+    "Iterator",
+    "Ord",
+    "PartialEq",
+    "PartialOrd",
+    "ToOwned",
+    "ToString",
+    "TryFrom",
+    "TryInto",
+];
+
+/// Macros that expand to code that can't be evaluated at compile time. `vec!` is only reported
+/// with elements, since `vec![]` is `Vec::new()`.
+const MACROS: &[&str] = &[
+    "dbg", "eprint", "eprintln", "format", "print", "println", "write", "writeln",
+];
+
+/// Checks the body of a function that is made `const` for constructs that can't be evaluated
+/// at compile time.
+///
+/// The check is syntactic, so it only reports what can't be `const` whatever the names in the
+/// body refer to, and leaves the rest to the compiler. Methods are never reported, since a
+/// method named like a trait method may be a `const fn` of the type, and neither are closures,
+/// which constant functions may create, nor trait objects, which they may coerce to. The check
+/// can still be skipped with `const(unchecked)`.
+pub(crate) fn check_const(body: &Block, const_token: Token![const], errors: &mut Vec<syn::Error>) {
+    let mut checker = ConstChecker { errors: Vec::new() };
+    checker.visit_block(body);
+
+    if !checker.errors.is_empty() {
+        errors.push(syn::Error::new(
+            const_token.span,
+            "cannot make this function `const`; use `const(unchecked)` to skip this check",
+        ));
+        errors.append(&mut checker.errors);
+    }
+}
+
+struct ConstChecker {
+    errors: Vec<syn::Error>,
+}
+
+impl ConstChecker {
+    fn report(&mut self, span: pm2::Span, message: &str) {
+        self.errors.push(syn::Error::new(span, message));
+    }
+}
+
+impl<'ast> Visit<'ast> for ConstChecker {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match expr {
+            Expr::ForLoop(expr_for_loop) => self.report(
+                expr_for_loop.for_token.span,
+                "`for` loops are not allowed in constant functions",
+            ),
+            Expr::Try(expr_try) => self.report(
+                expr_try.question_token.span,
+                "`?` is not allowed in constant functions",
+            ),
+            // Closures may be created but not called, so their bodies are never evaluated.
+            Expr::Closure(_) => return,
+            Expr::Async(expr_async) => {
+                self.report(
+                    expr_async.async_token.span,
+                    "async blocks are not allowed in constant functions",
+                );
+                return;
+            }
+            Expr::Call(expr_call) => {
+                if let Expr::Path(expr_path) = &*expr_call.func {
+                    if calls_trait_function(expr_path) {
+                        self.report(
+                            expr_path.span(),
+                            "trait functions cannot be called in constant functions",
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+        visit::visit_expr(self, expr);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        if let Some(segment) = mac.path.segments.last() {
+            let name = segment.ident.to_string();
+            if MACROS.contains(&&*name) || (name == "vec" && !mac.tokens.is_empty()) {
+                self.report(
+                    mac.path.span(),
+                    &format!("`{}!` cannot be used in constant functions", segment.ident),
+                );
+            }
+        }
+    }
+
+    fn visit_item(&mut self, _item: &'ast Item) {
+        // Nested items are checked on their own, if at all.
+    }
+}
+
+/// Returns whether a path calls a function through a trait, i.e. `<T as Trait>::function` or
+/// `Trait::function` for a trait of the standard library.
+fn calls_trait_function(expr_path: &ExprPath) -> bool {
+    if matches!(&expr_path.qself, Some(qself) if qself.as_token.is_some()) {
+        return true;
+    }
+    let segments = &expr_path.path.segments;
+    segments.len() >= 2 && TRAITS.contains(&&*segments[segments.len() - 2].ident.to_string())
+}
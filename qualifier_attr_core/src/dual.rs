@@ -0,0 +1,183 @@
+use syn::{
+    ext::IdentExt,
+    parse_quote,
+    visit_mut::{self, VisitMut},
+    Block, Expr, ExprClosure, ExprPath, Ident, Item, Signature,
+};
+
+use crate::{
+    helper::Qualify,
+    parse::{kw, Affix, Async, Change, Const, Dual, DualToggle, Qualifiers},
+};
+
+/// A function that gets a twin along with the other functions of its scope, so that the twins
+/// can call each other.
+pub(crate) struct Sibling {
+    name: String,
+    twin: Ident,
+    /// Whether the twin has the qualifier rather than the original.
+    adds: bool,
+}
+
+/// Returns the sibling of a function that gets a twin, or `None` if the twin can't be named,
+/// which `twin` reports.
+pub(crate) fn sibling(sig: &Signature, dual: &Dual) -> Option<Sibling> {
+    Some(Sibling {
+        name: sig.ident.unraw().to_string(),
+        twin: twin_ident(&sig.ident, dual).ok()?,
+        adds: !has_qualifier(sig, dual),
+    })
+}
+
+/// Returns a renamed copy of a function with the qualifier of `dual` toggled.
+///
+/// Removing `async` also strips `.await` from the body of the twin. Calls to the siblings whose
+/// twins are toggled the same way are turned into calls to their twins, which are awaited if
+/// `async` is added. Twins that add `unsafe` keep calling the safe originals instead.
+pub(crate) fn twin<T: Qualify + Clone>(
+    function: &T,
+    parts: fn(&mut T) -> (&mut Signature, Option<&mut Block>),
+    dual: &Dual,
+    siblings: &[Sibling],
+) -> syn::Result<T> {
+    let mut twin = function.clone();
+    let (sig, _) = parts(&mut twin);
+    let adds = !has_qualifier(sig, dual);
+    sig.ident = twin_ident(&sig.ident, dual)?;
+
+    let mut toggle = Qualifiers::default();
+    match dual.toggle {
+        DualToggle::Async(async_token) if !adds => {
+            toggle.asyncness = Some(Change::Clear(async_token.span));
+            toggle.strip_await = Some(kw::strip_await(async_token.span));
+        }
+        DualToggle::Async(async_token) => {
+            toggle.asyncness = Some(Change::Set(Async {
+                async_token,
+                desugar: None,
+            }));
+        }
+        DualToggle::Const(const_token) if !adds => {
+            toggle.constness = Some(Change::Clear(const_token.span));
+        }
+        DualToggle::Const(const_token) => {
+            toggle.constness = Some(Change::Set(Const {
+                const_token,
+                unchecked: None,
+            }));
+        }
+        DualToggle::Unsafe(unsafe_token) if !adds => {
+            toggle.unsafety = Some(Change::Clear(unsafe_token.span));
+        }
+        DualToggle::Unsafe(unsafe_token) => toggle.unsafety = Some(Change::Set(unsafe_token)),
+    }
+    twin.qualify().apply(toggle)?;
+
+    let rewrites_calls = !(adds && matches!(dual.toggle, DualToggle::Unsafe(_)));
+    if let (true, (_, Some(body))) = (rewrites_calls, parts(&mut twin)) {
+        CallRewriter {
+            siblings: siblings
+                .iter()
+                .filter(|sibling| sibling.adds == adds)
+                .collect(),
+            awaits: adds && matches!(dual.toggle, DualToggle::Async(_)),
+        }
+        .visit_block_mut(body);
+    }
+
+    Ok(twin)
+}
+
+/// Returns the name of the twin of a function.
+fn twin_ident(ident: &Ident, dual: &Dual) -> syn::Result<Ident> {
+    let name = ident.unraw().to_string();
+    let (twin_name, affix) = match &dual.affix {
+        Affix::Prefix(prefix) => (format!("{}{}", prefix.value(), name), prefix),
+        Affix::Suffix(suffix) => (format!("{}{}", name, suffix.value()), suffix),
+    };
+    let mut twin_ident = syn::parse_str::<Ident>(&twin_name).map_err(|_| {
+        syn::Error::new(
+            affix.span(),
+            format!("`{}` is not a valid name for the twin", twin_name),
+        )
+    })?;
+    twin_ident.set_span(ident.span());
+    Ok(twin_ident)
+}
+
+/// Returns whether a function has the qualifier that `dual` toggles.
+fn has_qualifier(sig: &Signature, dual: &Dual) -> bool {
+    match dual.toggle {
+        DualToggle::Async(_) => sig.asyncness.is_some(),
+        DualToggle::Const(_) => sig.constness.is_some(),
+        DualToggle::Unsafe(_) => sig.unsafety.is_some(),
+    }
+}
+
+/// Turns calls to siblings into calls to their twins, i.e. `sibling(...)`, `Self::sibling(...)`,
+/// and `self.sibling(...)`.
+struct CallRewriter<'a> {
+    siblings: Vec<&'a Sibling>,
+    /// Whether the twins are `async`, so that their futures have to be awaited.
+    awaits: bool,
+}
+
+impl CallRewriter<'_> {
+    fn rename(&self, ident: &mut Ident) -> bool {
+        match self
+            .siblings
+            .iter()
+            .find(|sibling| ident.unraw() == sibling.name)
+        {
+            Some(sibling) => {
+                let mut twin = sibling.twin.clone();
+                twin.set_span(ident.span());
+                *ident = twin;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl VisitMut for CallRewriter<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        visit_mut::visit_expr_mut(self, expr);
+
+        let renamed = match expr {
+            Expr::Call(call) => match &mut *call.func {
+                Expr::Path(ExprPath {
+                    qself: None, path, ..
+                }) if path.segments.len() == 1
+                    || (path.segments.len() == 2 && path.segments[0].ident == "Self") =>
+                {
+                    let last = path.segments.len() - 1;
+                    self.rename(&mut path.segments[last].ident)
+                }
+                _ => false,
+            },
+            Expr::MethodCall(call) => match &*call.receiver {
+                Expr::Path(receiver) if receiver.path.is_ident("self") => {
+                    self.rename(&mut call.method)
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+        if renamed && self.awaits {
+            let call = std::mem::replace(expr, Expr::PLACEHOLDER);
+            *expr = parse_quote!(#call.await);
+        }
+    }
+
+    fn visit_expr_closure_mut(&mut self, expr_closure: &mut ExprClosure) {
+        // Futures can't be awaited in closures.
+        if !self.awaits {
+            visit_mut::visit_expr_closure_mut(self, expr_closure);
+        }
+    }
+
+    fn visit_item_mut(&mut self, _: &mut Item) {
+        // Nested items have scopes of their own.
+    }
+}
@@ -0,0 +1,327 @@
+use syn::{ext::IdentExt, spanned::Spanned, Ident};
+use syn::{ForeignItem, ImplItem, Item, TraitItem};
+
+use crate::{
+    dual,
+    helper::Qualify,
+    parse::{Dual, Each, FlexibleForeignItem, MemberKind, Qualifiers},
+};
+
+/// Applies the qualifiers to every selected member of a module, implementation, trait, or
+/// `extern` block.
+///
+/// Only direct members are qualified; the members of nested modules are left alone.
+pub(crate) fn qualify_members(
+    item: &mut Item,
+    each: &Each,
+    dual: Option<&Dual>,
+    qualifiers: Qualifiers,
+) -> syn::Result<()> {
+    if let (Some(dual), Item::Trait(_) | Item::ForeignMod(_)) = (dual, &*item) {
+        return Err(syn::Error::new(
+            dual.dual_token.span,
+            "`dual` is only supported on the functions of modules and implementations",
+        ));
+    }
+
+    let mut errors = Vec::new();
+    // The symbols of the functions exported so far, to report duplicates.
+    let mut exported = Vec::new();
+
+    match item {
+        Item::Mod(item_mod) => match &mut item_mod.content {
+            Some((_, items)) => {
+                let mut duals = Vec::new();
+                for (i, item) in items.iter_mut().enumerate() {
+                    let description = describe_item(item);
+                    if qualify_member(
+                        item,
+                        description,
+                        false,
+                        each,
+                        &qualifiers,
+                        &mut exported,
+                        &mut errors,
+                    ) && matches!(item, Item::Fn(_))
+                    {
+                        duals.push(i);
+                    }
+                }
+                if let Some(dual) = dual {
+                    let functions = duals.iter().filter_map(|&i| match &items[i] {
+                        Item::Fn(item_fn) => Some((i, item_fn)),
+                        _ => None,
+                    });
+                    let siblings: Vec<_> = functions
+                        .clone()
+                        .filter_map(|(_, item_fn)| dual::sibling(&item_fn.sig, dual))
+                        .collect();
+                    let mut twins = Vec::new();
+                    for (i, item_fn) in functions {
+                        match dual::twin(
+                            item_fn,
+                            |item_fn| (&mut item_fn.sig, Some(&mut *item_fn.block)),
+                            dual,
+                            &siblings,
+                        ) {
+                            Ok(twin) => twins.push((i + 1, Item::Fn(twin))),
+                            Err(error) => errors.push(error),
+                        }
+                    }
+                    insert_twins(items, twins);
+                }
+            }
+            None => {
+                return Err(syn::Error::new(
+                    item_mod.semi.span(),
+                    "`each` requires an inline module",
+                ));
+            }
+        },
+        Item::Impl(item_impl) => {
+            if let (Some(dual), Some(_)) = (dual, &item_impl.trait_) {
+                return Err(syn::Error::new(
+                    dual.dual_token.span,
+                    "`dual` can't add methods to trait implementations",
+                ));
+            }
+            let mut duals = Vec::new();
+            for (i, item) in item_impl.items.iter_mut().enumerate() {
+                let description = describe_impl_item(item);
+                if qualify_member(
+                    item,
+                    description,
+                    item_impl.trait_.is_some(),
+                    each,
+                    &qualifiers,
+                    &mut exported,
+                    &mut errors,
+                ) && matches!(item, ImplItem::Fn(_))
+                {
+                    duals.push(i);
+                }
+            }
+            if let Some(dual) = dual {
+                let functions = duals.iter().filter_map(|&i| match &item_impl.items[i] {
+                    ImplItem::Fn(item_fn) => Some((i, item_fn)),
+                    _ => None,
+                });
+                let siblings: Vec<_> = functions
+                    .clone()
+                    .filter_map(|(_, item_fn)| dual::sibling(&item_fn.sig, dual))
+                    .collect();
+                let mut twins = Vec::new();
+                for (i, item_fn) in functions {
+                    match dual::twin(
+                        item_fn,
+                        |item_fn| (&mut item_fn.sig, Some(&mut item_fn.block)),
+                        dual,
+                        &siblings,
+                    ) {
+                        Ok(twin) => twins.push((i + 1, ImplItem::Fn(twin))),
+                        Err(error) => errors.push(error),
+                    }
+                }
+                insert_twins(&mut item_impl.items, twins);
+            }
+        }
+        Item::Trait(item_trait) => {
+            for item in &mut item_trait.items {
+                let description = describe_trait_item(item);
+                qualify_member(
+                    item,
+                    description,
+                    true,
+                    each,
+                    &qualifiers,
+                    &mut exported,
+                    &mut errors,
+                );
+            }
+        }
+        Item::ForeignMod(item_foreign_mod) => {
+            for item in &mut item_foreign_mod.items {
+                // `syn` can't represent `safe` foreign items, so functions and statics are
+                // qualified as flexible items instead.
+                if let Some(mut flexible) = FlexibleForeignItem::from_foreign_item(item) {
+                    let (kind, name) = describe_flexible_foreign_item(&flexible);
+                    qualify_member(
+                        &mut flexible,
+                        (kind, Some(name)),
+                        false,
+                        each,
+                        &qualifiers,
+                        &mut exported,
+                        &mut errors,
+                    );
+                    *item = flexible.into_foreign_item();
+                } else {
+                    let description = describe_foreign_item(item);
+                    qualify_member(
+                        item,
+                        description,
+                        false,
+                        each,
+                        &qualifiers,
+                        &mut exported,
+                        &mut errors,
+                    );
+                }
+            }
+        }
+        _ => {
+            return Err(syn::Error::new(
+                each.each_token.span,
+                "`each` is only supported on modules, implementations, traits, and `extern` blocks",
+            ));
+        }
+    }
+
+    if let Some(error) = errors.into_iter().reduce(|mut error, next| {
+        error.combine(next);
+        error
+    }) {
+        Err(error)
+    } else {
+        Ok(())
+    }
+}
+
+/// Applies the qualifiers to a single member if `each` selects it.
+///
+/// Returns whether the member was qualified.
+fn qualify_member<T: Qualify + Spanned>(
+    member: &mut T,
+    (kind, name): (Option<MemberKind>, Option<Ident>),
+    in_trait: bool,
+    each: &Each,
+    qualifiers: &Qualifiers,
+    exported: &mut Vec<String>,
+    errors: &mut Vec<syn::Error>,
+) -> bool {
+    let name = name.as_ref();
+    if !selects(each, kind, name) {
+        return false;
+    }
+
+    let span = match name {
+        Some(name) => name.span(),
+        None => member.span(),
+    };
+    let lens = match in_trait {
+        true => member.qualify().in_trait(),
+        false => member.qualify(),
+    };
+    if each.lenient.is_some() && !lens.supports(qualifiers) {
+        return false;
+    }
+    if let Err(error) = lens.apply(qualifiers.clone()) {
+        let mut member_error = syn::Error::new(
+            span,
+            "cannot qualify this member; use `each(lenient)` to skip unsupported members",
+        );
+        member_error.combine(error);
+        errors.push(member_error);
+        return false;
+    }
+
+    if let (Some(export), Some(name)) = (&qualifiers.export, name) {
+        let symbol = export.symbol(name);
+        if exported.contains(&symbol) {
+            errors.push(syn::Error::new(
+                name.span(),
+                format!("`{}` is already exported by another function", symbol),
+            ));
+        } else {
+            exported.push(symbol);
+        }
+    }
+    true
+}
+
+/// Inserts the twins emitted by `dual` after their originals, given the indices to insert them at.
+fn insert_twins<T>(members: &mut Vec<T>, twins: Vec<(usize, T)>) {
+    for (i, twin) in twins.into_iter().rev() {
+        members.insert(i, twin);
+    }
+}
+
+/// Returns whether `each` selects a member with the given kind and name.
+fn selects(each: &Each, kind: Option<MemberKind>, name: Option<&Ident>) -> bool {
+    let kind_matches =
+        each.kinds.is_empty() || kind.map_or(false, |kind| each.kinds.contains(&kind));
+    let name_matches = each.patterns.is_empty()
+        || name.map_or(false, |name| {
+            let name = name.unraw().to_string();
+            each.patterns
+                .iter()
+                .any(|pattern| glob_matches(pattern.value().as_bytes(), name.as_bytes()))
+        });
+    kind_matches && name_matches
+}
+
+/// Matches a name against a glob pattern, where `*` matches any run of characters and `?`
+/// matches a single character.
+fn glob_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => {
+            glob_matches(rest, name) || (!name.is_empty() && glob_matches(pattern, &name[1..]))
+        }
+        (Some((b'?', rest)), Some((_, name_rest))) => glob_matches(rest, name_rest),
+        (Some((p, rest)), Some((n, name_rest))) => p == n && glob_matches(rest, name_rest),
+        _ => false,
+    }
+}
+
+fn describe_item(item: &Item) -> (Option<MemberKind>, Option<Ident>) {
+    match item {
+        Item::Const(item) => (Some(MemberKind::Const), Some(item.ident.clone())),
+        Item::Enum(item) => (Some(MemberKind::Enum), Some(item.ident.clone())),
+        Item::Fn(item) => (Some(MemberKind::Fn), Some(item.sig.ident.clone())),
+        Item::Impl(_) => (Some(MemberKind::Impl), None),
+        Item::Mod(item) => (Some(MemberKind::Mod), Some(item.ident.clone())),
+        Item::Static(item) => (Some(MemberKind::Static), Some(item.ident.clone())),
+        Item::Struct(item) => (Some(MemberKind::Struct), Some(item.ident.clone())),
+        Item::Trait(item) => (Some(MemberKind::Trait), Some(item.ident.clone())),
+        Item::TraitAlias(item) => (Some(MemberKind::Trait), Some(item.ident.clone())),
+        Item::Type(item) => (Some(MemberKind::Type), Some(item.ident.clone())),
+        Item::Union(item) => (Some(MemberKind::Union), Some(item.ident.clone())),
+        Item::Use(_) => (Some(MemberKind::Use), None),
+        Item::ExternCrate(item) => (None, Some(item.ident.clone())),
+        Item::Macro(item) => (None, item.ident.clone()),
+        _ => (None, None),
+    }
+}
+
+pub(crate) fn describe_impl_item(item: &ImplItem) -> (Option<MemberKind>, Option<Ident>) {
+    match item {
+        ImplItem::Const(item) => (Some(MemberKind::Const), Some(item.ident.clone())),
+        ImplItem::Fn(item) => (Some(MemberKind::Fn), Some(item.sig.ident.clone())),
+        ImplItem::Type(item) => (Some(MemberKind::Type), Some(item.ident.clone())),
+        _ => (None, None),
+    }
+}
+
+pub(crate) fn describe_trait_item(item: &TraitItem) -> (Option<MemberKind>, Option<Ident>) {
+    match item {
+        TraitItem::Const(item) => (Some(MemberKind::Const), Some(item.ident.clone())),
+        TraitItem::Fn(item) => (Some(MemberKind::Fn), Some(item.sig.ident.clone())),
+        TraitItem::Type(item) => (Some(MemberKind::Type), Some(item.ident.clone())),
+        _ => (None, None),
+    }
+}
+
+fn describe_flexible_foreign_item(item: &FlexibleForeignItem) -> (Option<MemberKind>, Ident) {
+    match item {
+        FlexibleForeignItem::Fn(item) => (Some(MemberKind::Fn), item.sig.ident.clone()),
+        FlexibleForeignItem::Static(item) => (Some(MemberKind::Static), item.ident.clone()),
+    }
+}
+
+fn describe_foreign_item(item: &ForeignItem) -> (Option<MemberKind>, Option<Ident>) {
+    match item {
+        ForeignItem::Type(item) => (Some(MemberKind::Type), Some(item.ident.clone())),
+        _ => (None, None),
+    }
+}
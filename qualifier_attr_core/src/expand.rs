@@ -0,0 +1,275 @@
+//! The expansions of the attributes of `qualifier_attr`, for procedural macros that forward to
+//! them.
+//!
+//! Each function takes the arguments of the attribute and the item it is applied to, and returns
+//! the expanded item, or the item along with the errors.
+
+use pm2::TokenStream;
+use quote::quote;
+#[cfg(feature = "full")]
+use quote::ToTokens;
+#[cfg(feature = "full")]
+use syn::Item;
+
+use crate::cfg;
+use crate::parse::Qualifiers;
+#[cfg(any(feature = "token_backend", not(feature = "full")))]
+use crate::tokens;
+#[cfg(feature = "full")]
+use crate::{
+    dual, each, fields, header,
+    helper::Qualify,
+    markers, members,
+    parse::{FieldQualifiers, MemberQualifiers, QualifiedItem},
+    shim,
+};
+
+/// Expands `#[qualifiers(...)]`.
+pub fn qualifiers(meta: TokenStream, input: TokenStream) -> TokenStream {
+    fn inner(meta: TokenStream, input: &TokenStream) -> syn::Result<TokenStream> {
+        let qualifiers = syn::parse2::<Qualifiers>(meta)?;
+        let predicates = qualifiers.predicates();
+        let output = cfg::expand(&predicates, input, |holds| {
+            let resolved = qualifiers.resolve(holds)?;
+            // Only the combination that is built writes to headers.
+            #[cfg(feature = "full")]
+            let resolved = header::skip_unless_built(resolved, &predicates, holds)?;
+            qualify(resolved, input.clone())
+        })?;
+        Ok(output)
+    }
+
+    match inner(meta, &input) {
+        Ok(output) => output,
+        Err(error) => emit_error(error, input),
+    }
+}
+
+/// Emits the error next to the item it is about, so that the rest of the crate and IDEs still
+/// see the item.
+pub fn emit_error(error: syn::Error, item: TokenStream) -> TokenStream {
+    let error = error.into_compile_error();
+    quote!(#error #item)
+}
+
+/// Returns the item without its `#[qualify(...)]` helper attributes, or the tokens as they are if
+/// they are not an item.
+#[cfg(feature = "full")]
+fn strip_markers(input: &TokenStream) -> TokenStream {
+    match syn::parse2::<Item>(input.clone()) {
+        Ok(mut item) => {
+            markers::strip_markers(&mut item);
+            item.into_token_stream()
+        }
+        Err(_) => input.clone(),
+    }
+}
+
+/// Applies qualifiers without conditions to an item, and returns it along with the items emitted
+/// next to it.
+#[cfg(feature = "full")]
+fn qualify(mut qualifiers: Qualifiers, input: TokenStream) -> syn::Result<Vec<TokenStream>> {
+    // The token backend edits the header of the item when nothing else needs to change.
+    #[cfg(feature = "token_backend")]
+    if let Some(output) = tokens::qualify(&qualifiers, &input)? {
+        return Ok(vec![output]);
+    }
+
+    let dual = qualifiers.dual.take();
+    let extern_shim = qualifiers.extern_shim.take();
+
+    // Qualify the members of the item instead of the item itself.
+    if let Some(each) = qualifiers.each.take() {
+        if let Some(extern_shim) = extern_shim {
+            return Err(syn::Error::new(
+                extern_shim.extern_shim_token.span,
+                "`extern_shim` can't be combined with `each`",
+            ));
+        }
+        let mut input = syn::parse2::<Item>(input)?;
+        each::qualify_members(&mut input, &each, dual.as_ref(), qualifiers)?;
+        return Ok(vec![input.into_token_stream()]);
+    }
+
+    if let (Some(export), Some(_)) = (&qualifiers.export, &dual) {
+        return Err(syn::Error::new(
+            export.export_token.span,
+            "`export` can't be combined with `dual`, since the twin would be exported too",
+        ));
+    }
+    if let (Some(export), Some(_)) = (&qualifiers.export, &extern_shim) {
+        return Err(syn::Error::new(
+            export.export_token.span,
+            "`export` can't be combined with `extern_shim`, which exports the wrapper instead",
+        ));
+    }
+
+    // The item is classified by its keyword and parsed once, as a "flexible" item if possible.
+    let mut input = syn::parse2::<QualifiedItem>(input)?;
+
+    // Functions are the only items with twins and shims.
+    if dual.is_some() || extern_shim.is_some() {
+        let mut input = match input {
+            QualifiedItem::Fn(input) => input,
+            _ => {
+                return Err(match (&dual, &extern_shim) {
+                    (Some(dual), _) => syn::Error::new(
+                        dual.dual_token.span,
+                        "`dual` is only supported on functions; use `each` for the functions of modules and implementations",
+                    ),
+                    (None, Some(extern_shim)) => syn::Error::new(
+                        extern_shim.extern_shim_token.span,
+                        "`extern_shim` is only supported on functions",
+                    ),
+                    (None, None) => unreachable!(),
+                });
+            }
+        };
+        input.qualify().apply(qualifiers)?;
+        let twin = match &dual {
+            Some(dual) => {
+                // A recursive function is its own sibling.
+                let siblings: Vec<_> = dual::sibling(&input.sig, dual).into_iter().collect();
+                Some(dual::twin(
+                    &input,
+                    |input| (&mut input.sig, input.body.as_mut()),
+                    dual,
+                    &siblings,
+                )?)
+            }
+            None => None,
+        };
+        let shim = match &extern_shim {
+            Some(extern_shim) => Some(shim::shim(&input, extern_shim)?),
+            None => None,
+        };
+        let mut output = vec![input.into_token_stream()];
+        output.extend(twin.map(|twin| twin.into_token_stream()));
+        output.extend(shim);
+        return Ok(output);
+    }
+
+    input.qualify().apply(qualifiers)?;
+    Ok(vec![input.into_token_stream()])
+}
+
+/// Applies qualifiers without conditions to an item.
+#[cfg(not(feature = "full"))]
+fn qualify(qualifiers: Qualifiers, input: TokenStream) -> syn::Result<Vec<TokenStream>> {
+    // Without `full`, the token backend reports what it can't apply instead of falling back.
+    let output = tokens::qualify(&qualifiers, &input)?.ok_or_else(|| {
+        syn::Error::new(
+            pm2::Span::call_site(),
+            "these qualifiers require the `full` feature of `qualifier_attr`",
+        )
+    })?;
+    Ok(vec![output])
+}
+
+/// Reports an attribute that needs the full syntax tree of `syn`, next to the item it is applied
+/// to.
+#[cfg(not(feature = "full"))]
+fn requires_full(attribute: &str, input: TokenStream) -> TokenStream {
+    let error = syn::Error::new(
+        pm2::Span::call_site(),
+        format!(
+            "`#[{}]` requires the `full` feature of `qualifier_attr`",
+            attribute
+        ),
+    );
+    emit_error(error, input)
+}
+
+/// Expands `#[field_qualifiers(...)]`.
+#[cfg(feature = "full")]
+pub fn field_qualifiers(meta: TokenStream, input: TokenStream) -> TokenStream {
+    fn inner(meta: TokenStream, input: &TokenStream) -> syn::Result<TokenStream> {
+        let field_qualifiers = syn::parse2::<FieldQualifiers>(meta)?;
+        let mut item = syn::parse2::<Item>(input.clone())?;
+        let markers = fields::take_field_markers(&mut item)?;
+        // Only the fields with conditions are expanded into `cfg`-gated copies.
+        let errors = fields::qualify_fields(&mut item, &field_qualifiers, &markers)?;
+        Ok(quote!(#item #errors))
+    }
+
+    match inner(meta, &input) {
+        Ok(output) => output,
+        Err(error) => emit_error(error, strip_markers(&input)),
+    }
+}
+
+/// Expands `#[impl_qualifiers(...)]`.
+#[cfg(feature = "full")]
+pub fn impl_qualifiers(meta: TokenStream, input: TokenStream) -> TokenStream {
+    fn inner(meta: TokenStream, input: &TokenStream) -> syn::Result<TokenStream> {
+        let member_qualifiers = syn::parse2::<MemberQualifiers>(meta)?;
+        let mut item = syn::parse2::<Item>(input.clone())?;
+        // Only the members with conditions are expanded into `cfg`-gated copies.
+        let errors = members::qualify_impl_members(&mut item, &member_qualifiers)?;
+        Ok(quote!(#item #errors))
+    }
+
+    match inner(meta, &input) {
+        Ok(output) => output,
+        Err(error) => emit_error(error, input),
+    }
+}
+
+/// Expands `#[trait_qualifiers(...)]`.
+#[cfg(feature = "full")]
+pub fn trait_qualifiers(meta: TokenStream, input: TokenStream) -> TokenStream {
+    fn inner(meta: TokenStream, input: &TokenStream) -> syn::Result<TokenStream> {
+        let member_qualifiers = syn::parse2::<MemberQualifiers>(meta)?;
+        let mut item = syn::parse2::<Item>(input.clone())?;
+        // Only the members with conditions are expanded into `cfg`-gated copies.
+        let errors = members::qualify_trait_members(&mut item, &member_qualifiers)?;
+        Ok(quote!(#item #errors))
+    }
+
+    match inner(meta, &input) {
+        Ok(output) => output,
+        Err(error) => emit_error(error, input),
+    }
+}
+
+/// Expands `#[qualify_members]`.
+#[cfg(feature = "full")]
+pub fn qualify_members(meta: TokenStream, input: TokenStream) -> TokenStream {
+    fn inner(meta: TokenStream, input: &TokenStream) -> syn::Result<TokenStream> {
+        syn::parse2::<syn::parse::Nothing>(meta)?;
+        let mut item = syn::parse2::<Item>(input.clone())?;
+        let markers = members::take_member_markers(&mut item)?;
+        // Only the members with conditions are expanded into `cfg`-gated copies.
+        let errors = members::qualify_marked_members(&mut item, &markers)?;
+        Ok(quote!(#item #errors))
+    }
+
+    match inner(meta, &input) {
+        Ok(output) => output,
+        Err(error) => emit_error(error, strip_markers(&input)),
+    }
+}
+
+/// Expands `#[field_qualifiers(...)]`, which requires `full`.
+#[cfg(not(feature = "full"))]
+pub fn field_qualifiers(_meta: TokenStream, input: TokenStream) -> TokenStream {
+    requires_full("field_qualifiers", input)
+}
+
+/// Expands `#[impl_qualifiers(...)]`, which requires `full`.
+#[cfg(not(feature = "full"))]
+pub fn impl_qualifiers(_meta: TokenStream, input: TokenStream) -> TokenStream {
+    requires_full("impl_qualifiers", input)
+}
+
+/// Expands `#[trait_qualifiers(...)]`, which requires `full`.
+#[cfg(not(feature = "full"))]
+pub fn trait_qualifiers(_meta: TokenStream, input: TokenStream) -> TokenStream {
+    requires_full("trait_qualifiers", input)
+}
+
+/// Expands `#[qualify_members]`, which requires `full`.
+#[cfg(not(feature = "full"))]
+pub fn qualify_members(_meta: TokenStream, input: TokenStream) -> TokenStream {
+    requires_full("qualify_members", input)
+}
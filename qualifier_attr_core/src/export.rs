@@ -0,0 +1,34 @@
+use pm2::Span;
+use syn::{ext::IdentExt, parse_quote_spanned, Attribute, Ident, LitStr};
+
+/// Returns the attribute that exports a function with the given name under the given symbol,
+/// i.e. `#[no_mangle]` if they are the same and `#[export_name = "..."]` otherwise.
+///
+/// Both are unsafe attributes in edition 2024, which applies by the edition of the crate that
+/// the tokens are spanned to. With `unsafe_attribute`, they are wrapped in `unsafe(...)` as
+/// edition 2024 writes them. Otherwise, they are spanned at the mixed site of this crate, which
+/// is on edition 2021, so that crates on every edition accept them.
+pub(crate) fn symbol_attribute(
+    symbol: &str,
+    ident: &Ident,
+    span: Span,
+    unsafe_attribute: bool,
+) -> Attribute {
+    let symbol = LitStr::new(symbol, span);
+    let same_name = ident.unraw() == symbol.value();
+
+    if unsafe_attribute {
+        if same_name {
+            parse_quote_spanned!(span=> #[unsafe(no_mangle)])
+        } else {
+            parse_quote_spanned!(span=> #[unsafe(export_name = #symbol)])
+        }
+    } else {
+        let span = Span::mixed_site();
+        if same_name {
+            parse_quote_spanned!(span=> #[no_mangle])
+        } else {
+            parse_quote_spanned!(span=> #[export_name = #symbol])
+        }
+    }
+}
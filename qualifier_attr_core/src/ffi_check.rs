@@ -0,0 +1,133 @@
+use quote::ToTokens;
+use syn::{
+    punctuated::Punctuated, spanned::Spanned, Abi, FnArg, GenericArgument, GenericParam, Generics,
+    PathArguments, ReturnType, Token, Type,
+};
+
+/// Returns whether an ABI is meant for foreign code, as opposed to one of the Rust ABIs.
+pub(crate) fn is_foreign(abi: &Abi) -> bool {
+    match &abi.name {
+        Some(name) => !matches!(
+            &*name.value(),
+            "Rust" | "rust-call" | "rust-cold" | "rust-intrinsic"
+        ),
+        // `extern` on its own is `extern "C"`.
+        None => true,
+    }
+}
+
+/// Checks a function that is given a foreign ABI for parameter and return types that foreign
+/// code can't use.
+///
+/// The check is syntactic and only knows about obvious cases, so it can be skipped with
+/// `extern "ABI"(unchecked)`.
+pub(crate) fn check_ffi(
+    abi: &Abi,
+    generics: &Generics,
+    inputs: &Punctuated<FnArg, Token![,]>,
+    output: &ReturnType,
+    errors: &mut Vec<syn::Error>,
+) {
+    let mut ffi_errors = find_unsafe_types(generics, inputs, output);
+
+    if !ffi_errors.is_empty() {
+        let abi_string = abi.to_token_stream().to_string();
+        errors.push(syn::Error::new(
+            abi.span(),
+            format!(
+                "cannot make this function `{0}`; use `{0}(unchecked)` to skip this check",
+                abi_string
+            ),
+        ));
+        errors.append(&mut ffi_errors);
+    }
+}
+
+/// Reports the generic parameters and the parameter and return types of a function that are not
+/// FFI-safe.
+pub(crate) fn find_unsafe_types(
+    generics: &Generics,
+    inputs: &Punctuated<FnArg, Token![,]>,
+    output: &ReturnType,
+) -> Vec<syn::Error> {
+    let mut errors = Vec::new();
+
+    for param in &generics.params {
+        if let GenericParam::Type(_) | GenericParam::Const(_) = param {
+            errors.push(syn::Error::new(
+                param.span(),
+                "generic parameters are not FFI-safe; consider concrete types instead",
+            ));
+        }
+    }
+    for input in inputs {
+        if let FnArg::Typed(pat_type) = input {
+            check_type(&pat_type.ty, &mut errors);
+        }
+    }
+    if let ReturnType::Type(_, ty) = output {
+        check_type(ty, &mut errors);
+    }
+
+    errors
+}
+
+/// Reports the parts of a type that are not FFI-safe.
+fn check_type(ty: &Type, errors: &mut Vec<syn::Error>) {
+    match ty {
+        Type::Path(type_path) => {
+            if type_path.qself.is_some() {
+                return;
+            }
+            let segment = match type_path.path.segments.last() {
+                Some(segment) => segment,
+                None => return,
+            };
+            let message = match &*segment.ident.to_string() {
+                "String" | "str" => Some(
+                    "is not FFI-safe; consider `*const c_char` with `CStr` or `CString` instead",
+                ),
+                "Vec" => Some("is not FFI-safe; consider a pointer and a length instead"),
+                "char" => Some("is not FFI-safe; consider `u32` instead"),
+                _ => None,
+            };
+            match message {
+                Some(message) => errors.push(syn::Error::new(
+                    ty.span(),
+                    format!("`{}` {}", segment.ident, message),
+                )),
+                None => {
+                    if let PathArguments::AngleBracketed(arguments) = &segment.arguments {
+                        for argument in &arguments.args {
+                            if let GenericArgument::Type(ty) = argument {
+                                check_type(ty, errors);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Type::Reference(type_reference) => check_type(&type_reference.elem, errors),
+        Type::Ptr(type_ptr) => check_type(&type_ptr.elem, errors),
+        Type::Array(type_array) => check_type(&type_array.elem, errors),
+        Type::Paren(type_paren) => check_type(&type_paren.elem, errors),
+        Type::Group(type_group) => check_type(&type_group.elem, errors),
+        Type::Slice(_) => errors.push(syn::Error::new(
+            ty.span(),
+            "slices are not FFI-safe; consider a pointer and a length instead",
+        )),
+        Type::Tuple(type_tuple) if !type_tuple.elems.is_empty() => errors.push(syn::Error::new(
+            ty.span(),
+            "tuples are not FFI-safe; consider a `#[repr(C)]` struct instead",
+        )),
+        Type::TraitObject(_) => errors.push(syn::Error::new(
+            ty.span(),
+            "trait objects are not FFI-safe; consider an opaque pointer such as `*mut c_void` instead",
+        )),
+        Type::ImplTrait(_) => errors.push(syn::Error::new(
+            ty.span(),
+            "`impl Trait` is not FFI-safe; consider a concrete `#[repr(C)]` type instead",
+        )),
+        _ => {}
+    }
+}
@@ -0,0 +1,393 @@
+use std::collections::{HashMap, HashSet};
+
+use quote::ToTokens;
+use syn::{
+    ext::IdentExt, punctuated::Punctuated, spanned::Spanned, Field, Fields, Item, ItemStruct,
+    ItemUnion, Meta, RangeLimits, Token, Visibility,
+};
+
+use crate::{
+    cfg,
+    helper::Qualify,
+    markers::{self, take_markers, Marker},
+    parse::{push_predicate, Change, FieldQualifiers, FieldSelector, Qualifiers},
+};
+
+/// Applies the qualifiers of `#[field_qualifiers(...)]` and then the `#[qualify(...)]` helper
+/// attributes that were taken from the fields of a struct or union.
+///
+/// Fields that conditional qualifiers or helper attributes in `cfg_attr` apply to are expanded
+/// into `cfg`-gated copies, one for every combination of their own predicates, and the errors of
+/// single combinations are returned as gated items to emit next to the item. Errors that don't
+/// depend on the predicates, like unknown or duplicate selectors, are returned as errors.
+pub(crate) fn qualify_fields(
+    item: &mut Item,
+    FieldQualifiers(field_qualifiers): &FieldQualifiers,
+    markers: &[Vec<Marker>],
+) -> syn::Result<pm2::TokenStream> {
+    // NOTE: Remember to `.unraw()` field identifiers here! Otherwise, the
+    // usage of raw identifiers may cause unexpected behavior. For example,
+    // if `r#x` is used as the field name in the attribute, but `x` is the
+    // actual field name, if `.unraw()` is not used, the field will not be
+    // recognized!
+
+    // NOTE: If a duplicate field is present, only the first duplicate will be
+    // chosen. This isn't really a problem though, since duplicate fields are
+    // an error anyways.
+
+    let mut unit = Punctuated::new();
+    let (fields, is_tuple) = match fields_mut(item)? {
+        (Some(fields), is_tuple) => (fields, is_tuple),
+        (None, is_tuple) => (&mut unit, is_tuple),
+    };
+    let names = field_names(fields, is_tuple);
+
+    let mut errors = Vec::new();
+    // The selectors that select each field, in order.
+    let mut sources = vec![Vec::new(); fields.len()];
+    // Fields that have been selected by name or index, used for reporting duplicates.
+    let mut selected_by_name = HashMap::new();
+    // Selectors of every field or of every field of a type, used for reporting duplicates.
+    let mut selectors = HashSet::new();
+    let described: Vec<(&str, &Field)> = names.iter().map(String::as_str).zip(&*fields).collect();
+    for (selector, qualifiers) in field_qualifiers {
+        let selected = match select(selector, &described, is_tuple) {
+            Ok(selected) => selected,
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
+
+        let by_name = match selector {
+            FieldSelector::Name(_) | FieldSelector::Index(_) | FieldSelector::Range { .. } => true,
+            FieldSelector::All { .. } | FieldSelector::Type { .. } => {
+                let description = describe_selector(selector);
+                if !selectors.insert(description.clone()) {
+                    errors.push(syn::Error::new(
+                        selector.span(),
+                        format!("`{}` is already selected", description),
+                    ));
+                    continue;
+                }
+                false
+            }
+        };
+        for i in selected {
+            if by_name && selected_by_name.insert(i, selector.span()).is_some() {
+                errors.push(syn::Error::new(
+                    selector.span(),
+                    format!("field `{}` is already selected", names[i]),
+                ));
+                continue;
+            }
+            sources[i].push((selector, qualifiers));
+        }
+    }
+    if let Some(error) = errors.into_iter().reduce(|mut error, next| {
+        error.combine(next);
+        error
+    }) {
+        return Err(error);
+    }
+
+    let mut gated_errors = pm2::TokenStream::new();
+    let mut expanded = Punctuated::new();
+    for (i, field) in fields.iter().enumerate() {
+        let field_markers = markers.get(i).map_or(&[][..], Vec::as_slice);
+        let mut predicates = Vec::new();
+        for (_, qualifiers) in &sources[i] {
+            for predicate in qualifiers.predicates() {
+                push_predicate(&mut predicates, &predicate);
+            }
+        }
+        let predicates = markers::predicates(predicates, field_markers);
+        expanded.extend(cfg::expand_element(
+            field,
+            &predicates,
+            |field, gate| field.attrs.insert(0, gate),
+            |field, combination| {
+                qualify_field(field, &names[i], &sources[i], field_markers, &|predicate| {
+                    combination.holds(predicate)
+                })
+            },
+            &mut gated_errors,
+        )?);
+    }
+    *fields = expanded;
+    Ok(gated_errors)
+}
+
+/// Applies the qualifiers of the selectors that select a field, and then its helper attributes,
+/// for the combination of predicates that `holds` describes.
+fn qualify_field(
+    field: &mut Field,
+    name: &str,
+    sources: &[(&FieldSelector, &Qualifiers)],
+    markers: &[Marker],
+    holds: &dyn Fn(&Meta) -> bool,
+) -> syn::Result<()> {
+    let mut errors = Vec::new();
+    // The visibility that the field has been given so far, used for reporting overlapping
+    // selectors that give it different visibilities, e.g. `*(pub), x(pub(crate))`.
+    let mut visibility = None;
+    // The other qualifiers that the field has been given so far.
+    let mut given = Vec::new();
+    for (selector, qualifiers) in sources {
+        let qualifiers = match qualifiers.resolve(holds) {
+            Ok(qualifiers) => qualifiers,
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
+        if let Some(this) = qualifiers.visibility.as_ref().map(describe_visibility) {
+            match &visibility {
+                Some(other) if *other != this => errors.push(syn::Error::new(
+                    selector.span(),
+                    format!(
+                        "field `{}` is made `{}` here, but `{}` by another selector",
+                        name, this, other
+                    ),
+                )),
+                Some(_) => {}
+                None => visibility = Some(this),
+            }
+        }
+        for qualifier in other_qualifiers(&qualifiers) {
+            if given.contains(&qualifier) {
+                errors.push(syn::Error::new(
+                    selector.span(),
+                    format!(
+                        "field `{}` is already given `{}` by another selector",
+                        name, qualifier
+                    ),
+                ));
+            } else {
+                given.push(qualifier);
+            }
+        }
+        if let Err(error) = field.qualify().apply(qualifiers) {
+            errors.push(error);
+        }
+    }
+
+    for marker in markers {
+        let result = match marker.resolve(holds) {
+            Ok(Some(qualifiers)) => field.qualify().apply(qualifiers),
+            Ok(None) => Ok(()),
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            errors.push(error);
+        }
+    }
+
+    if let Some(error) = errors.into_iter().reduce(|mut error, next| {
+        error.combine(next);
+        error
+    }) {
+        Err(error)
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the qualifiers besides the visibility that are given, as they are written.
+fn other_qualifiers(qualifiers: &Qualifiers) -> Vec<&'static str> {
+    let given = [
+        ("exact", qualifiers.exact.is_some()),
+        ("export", qualifiers.export.is_some()),
+        ("default", qualifiers.defaultness.is_some()),
+        ("const", qualifiers.constness.is_some()),
+        ("async", qualifiers.asyncness.is_some()),
+        ("unsafe", qualifiers.unsafety.is_some()),
+        ("extern", qualifiers.abi.is_some()),
+        ("mut", qualifiers.mutability.is_some()),
+        ("auto", qualifiers.autoness.is_some()),
+        ("safe", qualifiers.safety.is_some()),
+    ];
+    given
+        .iter()
+        .filter(|(_, given)| *given)
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+/// Returns a selector as it is written in the attribute, e.g. `*` or `: String`.
+fn describe_selector(selector: &FieldSelector) -> String {
+    match selector {
+        FieldSelector::Name(name) => name.unraw().to_string(),
+        FieldSelector::Index(index) => index.to_string(),
+        FieldSelector::Range { start, limits, end } => {
+            format!("{}{}{}", start, limits.to_token_stream(), end)
+        }
+        FieldSelector::All { except, .. } if except.is_empty() => "*".to_owned(),
+        FieldSelector::All { except, .. } => format!(
+            "* except ({})",
+            except
+                .iter()
+                .map(describe_selector)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        FieldSelector::Type { ty, .. } => {
+            format!(": {}", ty.to_token_stream().to_string().replace(' ', ""))
+        }
+    }
+}
+
+/// Returns a visibility change as it is written in the attribute, e.g. `pub(crate)` or `priv`.
+fn describe_visibility(visibility: &Change<Visibility>) -> String {
+    match visibility {
+        Change::Set(visibility) => visibility.to_token_stream().to_string().replace(' ', ""),
+        Change::Clear(_) => "priv".to_owned(),
+    }
+}
+
+/// Removes the `#[qualify(...)]` helper attributes from the fields of a struct or union and
+/// returns them, in the order of the fields.
+pub(crate) fn take_field_markers(item: &mut Item) -> syn::Result<Vec<Vec<Marker>>> {
+    let mut errors = Vec::new();
+    let mut markers = Vec::new();
+
+    let (fields, _) = fields_mut(item)?;
+    for field in fields.into_iter().flatten() {
+        match take_markers(&mut field.attrs) {
+            Ok(field_markers) => markers.push(field_markers),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    if let Some(error) = errors.into_iter().reduce(|mut error, next| {
+        error.combine(next);
+        error
+    }) {
+        Err(error)
+    } else {
+        Ok(markers)
+    }
+}
+
+/// Returns the fields of a struct or union, or `None` for unit structs, and whether they are
+/// tuple fields.
+fn fields_mut(item: &mut Item) -> syn::Result<(Option<&mut Punctuated<Field, Token![,]>>, bool)> {
+    match item {
+        Item::Struct(ItemStruct {
+            fields: Fields::Named(fields),
+            ..
+        })
+        | Item::Union(ItemUnion { fields, .. }) => Ok((Some(&mut fields.named), false)),
+        Item::Struct(ItemStruct {
+            fields: Fields::Unnamed(fields),
+            ..
+        }) => Ok((Some(&mut fields.unnamed), true)),
+        Item::Struct(ItemStruct {
+            fields: Fields::Unit,
+            ..
+        }) => Ok((None, false)),
+        _ => Err(syn::Error::new(
+            item.span(),
+            "this item does not support field qualifiers",
+        )),
+    }
+}
+
+/// Returns the names of fields, e.g. `x`, or `_0` for the first tuple field.
+fn field_names(fields: &Punctuated<Field, Token![,]>, is_tuple: bool) -> Vec<String> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match (&field.ident, is_tuple) {
+            (Some(ident), false) => ident.unraw().to_string(),
+            _ => format!("_{}", i),
+        })
+        .collect()
+}
+
+/// Returns the indices of the fields that the selector selects.
+fn select(
+    selector: &FieldSelector,
+    fields: &[(&str, &Field)],
+    is_tuple: bool,
+) -> syn::Result<Vec<usize>> {
+    match selector {
+        FieldSelector::Name(name) => {
+            let name_string = name.unraw().to_string();
+            match fields
+                .iter()
+                .position(|(field_name, _)| *field_name == name_string)
+            {
+                Some(i) => Ok(vec![i]),
+                None => Err(syn::Error::new(
+                    name.span(),
+                    format!("unknown field `{}`", name),
+                )),
+            }
+        }
+        FieldSelector::Index(index) => {
+            let i = parse_index(index, is_tuple)?;
+            if i < fields.len() {
+                Ok(vec![i])
+            } else {
+                Err(syn::Error::new(
+                    index.span(),
+                    format!("unknown field `{}`", i),
+                ))
+            }
+        }
+        FieldSelector::Range { start, limits, end } => {
+            let first = parse_index(start, is_tuple)?;
+            let last = match limits {
+                RangeLimits::HalfOpen(_) => parse_index(end, is_tuple)?.checked_sub(1),
+                RangeLimits::Closed(_) => Some(parse_index(end, is_tuple)?),
+            };
+            match last {
+                Some(last) if first <= last && last < fields.len() => Ok((first..=last).collect()),
+                Some(last) if first <= last => Err(syn::Error::new(
+                    end.span(),
+                    format!("unknown field `{}`", last),
+                )),
+                _ => Err(syn::Error::new(start.span(), "empty field range")),
+            }
+        }
+        FieldSelector::All { except, .. } => {
+            let mut excluded = Vec::new();
+            for exception in except {
+                excluded.extend(select(exception, fields, is_tuple)?);
+            }
+            Ok((0..fields.len())
+                .filter(|i| !excluded.contains(i))
+                .collect())
+        }
+        FieldSelector::Type { ty, .. } => {
+            // NOTE: Types are compared syntactically, so `String` and
+            // `std::string::String` are different types here.
+            let ty_string = ty.to_token_stream().to_string();
+            let selected: Vec<usize> = fields
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, field))| field.ty.to_token_stream().to_string() == ty_string)
+                .map(|(i, _)| i)
+                .collect();
+            if selected.is_empty() {
+                Err(syn::Error::new(
+                    ty.span(),
+                    format!("no field has the type `{}`", ty_string),
+                ))
+            } else {
+                Ok(selected)
+            }
+        }
+    }
+}
+
+fn parse_index(index: &syn::LitInt, is_tuple: bool) -> syn::Result<usize> {
+    if !is_tuple {
+        return Err(syn::Error::new(
+            index.span(),
+            "field indices are only supported on tuple structs",
+        ));
+    }
+    index.base10_parse()
+}
@@ -0,0 +1,558 @@
+use std::{
+    fs, io,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use pm2::Span;
+use quote::ToTokens;
+use syn::{
+    ext::IdentExt, punctuated::Punctuated, spanned::Spanned, Abi, Attribute, Expr, ExprLit, FnArg,
+    GenericArgument, Generics, Ident, Lit, LitStr, Meta, Pat, PathArguments, ReturnType, Token,
+    Type, TypePath,
+};
+
+use crate::parse::{Change, Extern, Qualifiers};
+
+/// The file under `OUT_DIR` that `qualifier_attr_build::emit_cfgs` records the cfgs that the
+/// crate is built with in, one per line, e.g. `unix` or `feature="ffi"`.
+const CFGS_FILE: &str = "qualifier_attr_cfgs";
+
+/// The prefix of the features that are recorded as cargo before 1.85 tells them to build scripts,
+/// in upper case with `_` for `-`, e.g. `qualifier_attr_feature=MY_FEATURE`.
+const NORMALIZED_FEATURE: &str = "qualifier_attr_feature";
+
+/// The cfgs that build scripts can't see, so the record can't tell whether they hold.
+const UNRECORDED_CFGS: &[&str] = &["doc", "doctest", "test"];
+
+/// The directory under `OUT_DIR` that the prototypes are written to, one directory per header,
+/// which `qualifier_attr_build::emit_cfgs` clears when the build script runs.
+const PROTOTYPES_DIR: &str = "qualifier_attr_prototypes";
+
+/// Numbers the temporary files of this process, so that they never clash.
+static TEMPORARY_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// C types of the primitive Rust types.
+const PRIMITIVES: &[(&str, &str)] = &[
+    ("bool", "bool"),
+    ("f32", "float"),
+    ("f64", "double"),
+    ("i16", "int16_t"),
+    ("i32", "int32_t"),
+    ("i64", "int64_t"),
+    ("i8", "int8_t"),
+    ("isize", "intptr_t"),
+    ("u16", "uint16_t"),
+    ("u32", "uint32_t"),
+    ("u64", "uint64_t"),
+    ("u8", "uint8_t"),
+    ("usize", "uintptr_t"),
+];
+
+/// C types of the type aliases in `core::ffi`, `std::os::raw`, and `libc`.
+const C_TYPES: &[(&str, &str)] = &[
+    ("c_char", "char"),
+    ("c_double", "double"),
+    ("c_float", "float"),
+    ("c_int", "int"),
+    ("c_long", "long"),
+    ("c_longlong", "long long"),
+    ("c_schar", "signed char"),
+    ("c_short", "short"),
+    ("c_uchar", "unsigned char"),
+    ("c_uint", "unsigned int"),
+    ("c_ulong", "unsigned long"),
+    ("c_ulonglong", "unsigned long long"),
+    ("c_ushort", "unsigned short"),
+    ("c_void", "void"),
+];
+
+/// The parts of a function that its C prototype is made of.
+pub(crate) struct Prototype<'a> {
+    pub attrs: &'a [Attribute],
+    pub ident: &'a Ident,
+    pub generics: &'a Generics,
+    pub inputs: &'a Punctuated<FnArg, Token![,]>,
+    pub output: &'a ReturnType,
+}
+
+/// Writes the C prototype of a function that is given `extern "C"(header = "...")` to the header
+/// under `OUT_DIR`.
+///
+/// Every prototype is written to a file of its own in `qualifier_attr_prototypes/<header>`, and
+/// the header is then put together from every file in that directory, so it lists the functions
+/// of every invocation of the attribute in the crate. Several compilations of the crate may run
+/// at once, so the directory is never cleared here. `qualifier_attr_build::emit_cfgs` clears it
+/// instead, which drops the functions that were renamed or removed since.
+pub(crate) fn write_prototype(
+    abi: &Abi,
+    header: &LitStr,
+    prototype: Prototype,
+    errors: &mut Vec<syn::Error>,
+) {
+    let file_name = header.value();
+    if file_name.is_empty()
+        || file_name == "."
+        || file_name == ".."
+        || file_name.contains(['/', '\\'])
+    {
+        errors.push(syn::Error::new(
+            header.span(),
+            "expected the file name of the header, e.g. `\"mylib.h\"`",
+        ));
+        return;
+    }
+    if abi.name.as_ref().map_or(false, |name| name.value() != "C") {
+        errors.push(syn::Error::new(
+            abi.span(),
+            "only `extern \"C\"` functions can be written to a header",
+        ));
+        return;
+    }
+
+    let name = match symbol_name(prototype.attrs, prototype.ident) {
+        Some(name) => name,
+        None => {
+            errors.push(syn::Error::new(
+                prototype.ident.span(),
+                "the symbol of this function is mangled, so C can't link to it; export it with `export`, `#[no_mangle]`, or `#[export_name]`",
+            ));
+            return;
+        }
+    };
+
+    let mut type_errors = Vec::new();
+    let declaration = declare(prototype, &name, &mut type_errors);
+    if !type_errors.is_empty() {
+        errors.push(syn::Error::new(
+            header.span(),
+            format!(
+                "cannot write this function to `{}`; its prototype has types without a C equivalent",
+                file_name
+            ),
+        ));
+        errors.append(&mut type_errors);
+        return;
+    }
+
+    let out_dir = match std::env::var_os("OUT_DIR") {
+        Some(out_dir) => out_dir,
+        None => {
+            errors.push(syn::Error::new(
+                header.span(),
+                "`header` writes to `OUT_DIR`, which is only set for crates with a build script",
+            ));
+            return;
+        }
+    };
+    if !Path::new(&out_dir).join(CFGS_FILE).exists() {
+        errors.push(syn::Error::new(
+            header.span(),
+            "`header` needs `qualifier_attr_build::emit_cfgs()` in the build script, which clears the functions of earlier builds from the header",
+        ));
+        return;
+    }
+    if let Err(error) = write_header(Path::new(&out_dir), &file_name, &name, &declaration) {
+        errors.push(syn::Error::new(
+            header.span(),
+            format!("failed to write `{}`: {}", file_name, error),
+        ));
+    }
+}
+
+/// Removes the header from qualifiers that were resolved for a combination of `cfg` predicates
+/// other than the one that the crate is built with, so that only functions that are built are
+/// written to the header.
+pub(crate) fn skip_unless_built(
+    mut qualifiers: Qualifiers,
+    predicates: &[Meta],
+    holds: &dyn Fn(&Meta) -> bool,
+) -> syn::Result<Qualifiers> {
+    let header = match &mut qualifiers.abi {
+        Some(Change::Set(Extern {
+            header: header @ Some(_),
+            ..
+        })) if !predicates.is_empty() => header,
+        _ => return Ok(qualifiers),
+    };
+
+    let span = header.as_ref().map_or_else(Span::call_site, LitStr::span);
+    let cfgs = recorded_cfgs(span)?;
+    for predicate in predicates {
+        if evaluate(predicate, &cfgs)? != holds(predicate) {
+            *header = None;
+            break;
+        }
+    }
+    Ok(qualifiers)
+}
+
+/// Returns the cfgs that `qualifier_attr_build::emit_cfgs` recorded for the crate.
+fn recorded_cfgs(span: Span) -> syn::Result<Vec<String>> {
+    let contents = std::env::var_os("OUT_DIR")
+        .and_then(|out_dir| fs::read_to_string(Path::new(&out_dir).join(CFGS_FILE)).ok());
+    match contents {
+        Some(contents) => Ok(contents.lines().map(str::to_owned).collect()),
+        None => Err(syn::Error::new(
+            span,
+            "`header` with conditional qualifiers needs `qualifier_attr_build::emit_cfgs()` in the build script, which tells it which `cfg` predicates hold",
+        )),
+    }
+}
+
+/// Evaluates a `cfg` predicate against the recorded cfgs.
+fn evaluate(predicate: &Meta, cfgs: &[String]) -> syn::Result<bool> {
+    let name = match predicate.path().get_ident() {
+        Some(name) => name.to_string(),
+        None => {
+            return Err(syn::Error::new(
+                predicate.span(),
+                "expected a `cfg` predicate",
+            ))
+        }
+    };
+    match predicate {
+        Meta::Path(_) if UNRECORDED_CFGS.contains(&&*name) => Err(syn::Error::new(
+            predicate.span(),
+            format!(
+                "`header` can't tell whether `{}` holds, since build scripts can't see it",
+                name
+            ),
+        )),
+        Meta::Path(_) => Ok(cfgs.contains(&name)),
+        Meta::NameValue(meta) => match &meta.value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(value),
+                ..
+            }) => {
+                let value = value.value();
+                if cfgs.contains(&format!("{}=\"{}\"", name, value)) {
+                    return Ok(true);
+                }
+                // Cargo before 1.85 only tells build scripts the features in upper case with `_`
+                // for `-`, which can't tell `my-feature` and `my_feature` apart.
+                let normalized = value.to_uppercase().replace('-', "_");
+                if name == "feature"
+                    && cfgs.contains(&format!("{}={}", NORMALIZED_FEATURE, normalized))
+                {
+                    return Err(syn::Error::new(
+                        meta.value.span(),
+                        format!(
+                            "`header` can't tell whether `feature = \"{}\"` holds, since cargo before 1.85 doesn't tell build scripts the names of features",
+                            value
+                        ),
+                    ));
+                }
+                Ok(false)
+            }
+            value => Err(syn::Error::new(value.span(), "expected a string literal")),
+        },
+        Meta::List(list) => {
+            let nested = list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+            match &*name {
+                "all" => {
+                    for predicate in &nested {
+                        if !evaluate(predicate, cfgs)? {
+                            return Ok(false);
+                        }
+                    }
+                    Ok(true)
+                }
+                "any" => {
+                    for predicate in &nested {
+                        if evaluate(predicate, cfgs)? {
+                            return Ok(true);
+                        }
+                    }
+                    Ok(false)
+                }
+                "not" if nested.len() == 1 => Ok(!evaluate(&nested[0], cfgs)?),
+                _ => Err(syn::Error::new(
+                    list.span(),
+                    "expected `all(...)`, `any(...)`, or `not(...)`",
+                )),
+            }
+        }
+    }
+}
+
+/// Returns the C prototype of the function, which is named after its symbol.
+fn declare(prototype: Prototype, name: &str, errors: &mut Vec<syn::Error>) -> String {
+    let Prototype {
+        generics,
+        inputs,
+        output,
+        ..
+    } = prototype;
+
+    if !generics.params.is_empty() {
+        errors.push(syn::Error::new(
+            generics.span(),
+            "generic functions have no C equivalent",
+        ));
+    }
+
+    let mut parameters = Vec::new();
+    for input in inputs {
+        match input {
+            FnArg::Receiver(receiver) => errors.push(syn::Error::new(
+                receiver.span(),
+                "methods have no C equivalent; consider a free function instead",
+            )),
+            FnArg::Typed(pat_type) => {
+                let parameter_name = match &*pat_type.pat {
+                    Pat::Ident(pat_ident) => Some(pat_ident.ident.unraw().to_string()),
+                    _ => None,
+                };
+                match c_type(&pat_type.ty) {
+                    Ok(ty) if ty == "void" => errors.push(syn::Error::new(
+                        pat_type.ty.span(),
+                        "parameters can't have a `void` type in C",
+                    )),
+                    Ok(ty) => parameters.push(match parameter_name {
+                        Some(parameter_name) => declarator(&ty, &parameter_name),
+                        None => ty,
+                    }),
+                    Err(error) => errors.push(error),
+                }
+            }
+        }
+    }
+    if parameters.is_empty() {
+        parameters.push("void".to_owned());
+    }
+
+    let return_type = match output {
+        ReturnType::Default => "void".to_owned(),
+        ReturnType::Type(_, ty) => c_type(ty).unwrap_or_else(|error| {
+            errors.push(error);
+            String::new()
+        }),
+    };
+
+    format!(
+        "{}({});\n",
+        declarator(&return_type, name),
+        parameters.join(", ")
+    )
+}
+
+/// Returns the symbol of a function that is exported with `#[no_mangle]` or
+/// `#[export_name = "..."]`, either of which may be wrapped in `unsafe(...)`, or `None` if its
+/// symbol is mangled.
+fn symbol_name(attrs: &[Attribute], ident: &Ident) -> Option<String> {
+    attrs.iter().find_map(|attr| {
+        let meta = match &attr.meta {
+            Meta::List(list) if list.path.is_ident("unsafe") => list.parse_args::<Meta>().ok()?,
+            meta => meta.clone(),
+        };
+        match &meta {
+            Meta::Path(path) if path.is_ident("no_mangle") => Some(ident.unraw().to_string()),
+            Meta::NameValue(meta) if meta.path.is_ident("export_name") => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(name),
+                    ..
+                }) => Some(name.value()),
+                _ => None,
+            },
+            _ => None,
+        }
+    })
+}
+
+/// Declares a name with a C type, e.g. `const char *name`.
+fn declarator(ty: &str, name: &str) -> String {
+    if ty.ends_with('*') {
+        format!("{}{}", ty, name)
+    } else {
+        format!("{} {}", ty, name)
+    }
+}
+
+/// Returns a pointer type to a C type.
+fn pointer(pointee: String, is_const: bool) -> String {
+    match (is_const, pointee.ends_with('*')) {
+        (false, false) => format!("{} *", pointee),
+        (false, true) => format!("{}*", pointee),
+        (true, false) => format!("const {} *", pointee),
+        (true, true) => format!("{}const *", pointee),
+    }
+}
+
+/// Maps a Rust type to a C type.
+///
+/// Named types besides the primitives and the C type aliases are assumed to be `#[repr(C)]`
+/// types that are declared to C elsewhere, and keep their names.
+fn c_type(ty: &Type) -> syn::Result<String> {
+    let message = match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => return c_path_type(type_path),
+        Type::Ptr(type_ptr) => {
+            return Ok(pointer(
+                c_type(&type_ptr.elem)?,
+                type_ptr.const_token.is_some(),
+            ))
+        }
+        Type::Reference(type_reference) => {
+            return Ok(pointer(
+                c_type(&type_reference.elem)?,
+                type_reference.mutability.is_none(),
+            ))
+        }
+        Type::Paren(type_paren) => return c_type(&type_paren.elem),
+        Type::Group(type_group) => return c_type(&type_group.elem),
+        Type::Tuple(type_tuple) if type_tuple.elems.is_empty() => return Ok("void".to_owned()),
+        Type::Never(_) => return Ok("void".to_owned()),
+        Type::Array(_) => "arrays have no C equivalent outside of structs; consider a pointer to the first element instead",
+        Type::Slice(_) => "slices have no C equivalent; consider a pointer and a length instead",
+        Type::Tuple(_) => "tuples have no C equivalent; consider a `#[repr(C)]` struct instead",
+        Type::BareFn(_) => "function pointers can't be written to a header; consider a `#[repr(C)]` type alias instead",
+        Type::TraitObject(_) => "trait objects have no C equivalent; consider `*mut c_void` instead",
+        Type::ImplTrait(_) => "`impl Trait` has no C equivalent",
+        _ => "this type has no C equivalent",
+    };
+    Err(syn::Error::new(ty.span(), message))
+}
+
+fn c_path_type(type_path: &TypePath) -> syn::Result<String> {
+    let segment = match type_path.path.segments.last() {
+        Some(segment) => segment,
+        None => return Err(syn::Error::new(type_path.span(), "expected a type")),
+    };
+    let name = segment.ident.unraw().to_string();
+
+    match &segment.arguments {
+        PathArguments::None => {}
+        PathArguments::AngleBracketed(arguments) if arguments.args.len() == 1 => {
+            if let Some(GenericArgument::Type(argument)) = arguments.args.first() {
+                match &*name {
+                    // `None` is the null pointer.
+                    "Option" if is_pointer(argument) => return c_type(argument),
+                    "NonNull" => return Ok(pointer(c_type(argument)?, false)),
+                    _ => {}
+                }
+            }
+            return Err(generic_type_error(type_path));
+        }
+        _ => return Err(generic_type_error(type_path)),
+    }
+
+    if let Some((_, c)) = PRIMITIVES
+        .iter()
+        .chain(C_TYPES)
+        .find(|(rust, _)| *rust == name)
+    {
+        return Ok((*c).to_owned());
+    }
+    let message = match &*name {
+        "String" | "str" => format!(
+            "`{}` has no C equivalent; consider `*const c_char` with `CStr` or `CString` instead",
+            name
+        ),
+        "char" => "`char` has no C equivalent; consider `u32` instead".to_owned(),
+        "i128" | "u128" => format!("`{}` has no portable C equivalent", name),
+        "Self" => {
+            "`Self` can't be written to a header; consider the name of the type instead".to_owned()
+        }
+        _ => return Ok(name),
+    };
+    Err(syn::Error::new(type_path.span(), message))
+}
+
+fn generic_type_error(type_path: &TypePath) -> syn::Error {
+    syn::Error::new(
+        type_path.span(),
+        format!(
+            "`{}` has no C equivalent; only `Option` of references and `NonNull` are supported",
+            type_path.to_token_stream().to_string().replace(' ', "")
+        ),
+    )
+}
+
+/// Returns whether `Option` of the type is represented as a nullable pointer.
+fn is_pointer(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(_) => true,
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map_or(false, |segment| segment.ident == "NonNull"),
+        Type::Paren(type_paren) => is_pointer(&type_paren.elem),
+        Type::Group(type_group) => is_pointer(&type_group.elem),
+        _ => false,
+    }
+}
+
+/// Writes the declaration of a function to its own file and puts the header together again.
+///
+/// Files are only written when their contents change, so that build tools watching the header
+/// don't rebuild needlessly.
+fn write_header(out_dir: &Path, file_name: &str, name: &str, declaration: &str) -> io::Result<()> {
+    let fragments = out_dir.join(PROTOTYPES_DIR).join(file_name);
+    fs::create_dir_all(&fragments)?;
+    write_if_changed(&fragments.join(format!("{}.h", name)), declaration)?;
+
+    // Temporary files of other compilations are left out.
+    let mut fragment_paths = fs::read_dir(&fragments)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .filter(|path| {
+            path.as_ref().map_or(true, |path| {
+                path.extension().map_or(false, |ext| ext == "h")
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    fragment_paths.sort();
+
+    let guard = header_guard(file_name);
+    let mut contents = format!(
+        "/* Generated by qualifier_attr. Do not edit. */\n\n\
+         #ifndef {0}\n#define {0}\n\n\
+         #include <stdbool.h>\n#include <stdint.h>\n\n\
+         #ifdef __cplusplus\nextern \"C\" {{\n#endif\n\n",
+        guard
+    );
+    for path in fragment_paths {
+        contents.push_str(&fs::read_to_string(path)?);
+    }
+    contents.push_str(&format!(
+        "\n#ifdef __cplusplus\n}}\n#endif\n\n#endif /* {} */\n",
+        guard
+    ));
+    write_if_changed(&out_dir.join(file_name), &contents)
+}
+
+/// Writes a file unless it already has the contents.
+///
+/// The contents are written to a temporary file that then replaces the file, so that other
+/// compilations reading it never see it half-written.
+fn write_if_changed(path: &Path, contents: &str) -> io::Result<()> {
+    if fs::read_to_string(path).map_or(false, |existing| existing == contents) {
+        return Ok(());
+    }
+    let temporary = path.with_extension(format!(
+        "{}.{}.tmp",
+        std::process::id(),
+        TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path).map_err(|error| {
+        let _ = fs::remove_file(&temporary);
+        error
+    })
+}
+
+/// Returns the include guard of a header, e.g. `MYLIB_H` for `mylib.h`.
+fn header_guard(file_name: &str) -> String {
+    let mut guard = String::new();
+    if file_name.starts_with(|c: char| c.is_ascii_digit()) {
+        guard.push('_');
+    }
+    guard.extend(file_name.chars().map(|c| {
+        if c.is_ascii_alphanumeric() {
+            c.to_ascii_uppercase()
+        } else {
+            '_'
+        }
+    }));
+    guard
+}
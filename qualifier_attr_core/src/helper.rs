@@ -0,0 +1,943 @@
+use quote::{quote_spanned, ToTokens};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{parse_quote_spanned, Attribute, Block, FnArg, Ident, ReturnType, Stmt};
+use syn::{Abi, Generics, Signature, StaticMutability, Token, TypeParamBound, Visibility};
+
+use syn::Field;
+use syn::{ForeignItem, ForeignItemFn, ForeignItemMacro, ForeignItemStatic, ForeignItemType};
+use syn::{ImplItem, ImplItemConst, ImplItemFn, ImplItemMacro, ImplItemType};
+use syn::{
+    Item, ItemConst, ItemEnum, ItemExternCrate, ItemFn, ItemForeignMod, ItemImpl, ItemMacro,
+    ItemMod, ItemStatic, ItemStruct, ItemTrait, ItemTraitAlias, ItemType, ItemUnion, ItemUse,
+};
+use syn::{TraitItem, TraitItemConst, TraitItemFn, TraitItemMacro, TraitItemType};
+
+use crate::parse::{
+    kw, Async, AsyncDesugar, Change, Extern, FlexibleForeignItem, FlexibleItemConst,
+    FlexibleItemFn, FlexibleItemStatic, FlexibleItemType, QualifiedItem, Qualifiers,
+};
+use crate::{
+    capture, const_check, export, ffi_check,
+    header::{self, Prototype},
+    strip_await,
+};
+
+/// Qualification [lens](https://rust-unofficial.github.io/patterns/functional/lenses.html).
+///
+/// It is built from the parts of an item that hold qualifiers, and applies qualifiers to them.
+/// Qualifiers without a part in the lens are reported as not supported.
+pub struct QualifiersLens<'a> {
+    visibility: Option<&'a mut Visibility>,
+    defaultness: Option<&'a mut Option<Token![default]>>,
+    constness: Option<&'a mut Option<Token![const]>>,
+    asyncness: Option<&'a mut Option<Token![async]>>,
+    unsafety: Option<&'a mut Option<Token![unsafe]>>,
+    abi: Option<&'a mut dyn Slot<Abi>>,
+    mutability: Option<&'a mut StaticMutability>,
+    autoness: Option<AutoTrait<'a>>,
+    safety: Option<&'a mut Option<kw::safe>>,
+    function: Option<FnParts<'a>>,
+}
+
+/// The parts of a trait that decide whether it may become an auto trait.
+pub struct AutoTrait<'a> {
+    pub auto_token: &'a mut Option<Token![auto]>,
+    pub generics: &'a Generics,
+    pub supertraits: &'a Punctuated<TypeParamBound, Token![+]>,
+    pub items: &'a [TraitItem],
+}
+
+impl AutoTrait<'_> {
+    /// Reports everything that prevents the trait from being an auto trait.
+    ///
+    /// Returns `true` if nothing was reported.
+    fn check(&self, errors: &mut Vec<syn::Error>) -> bool {
+        let errors_before = errors.len();
+        if !self.generics.params.is_empty() || self.generics.where_clause.is_some() {
+            errors.push(syn::Error::new(
+                self.generics.span(),
+                "auto traits cannot have generic parameters or where clauses",
+            ));
+        }
+        if !self.supertraits.is_empty() {
+            errors.push(syn::Error::new(
+                self.supertraits.span(),
+                "auto traits cannot have supertraits",
+            ));
+        }
+        if let Some(item) = self.items.first() {
+            errors.push(syn::Error::new(
+                item.span(),
+                "auto traits cannot have associated items",
+            ));
+        }
+        errors.len() == errors_before
+    }
+}
+
+/// The parts of a function besides its qualifiers, which some qualifiers check or rewrite.
+struct FnParts<'a> {
+    attrs: &'a mut Vec<Attribute>,
+    ident: &'a Ident,
+    generics: &'a mut Generics,
+    inputs: &'a mut Punctuated<FnArg, Token![,]>,
+    output: &'a mut ReturnType,
+    body: Option<&'a mut Block>,
+    /// Whether a desugared `impl Future` has to name the lifetimes it captures, which is the case
+    /// outside traits and their implementations.
+    captures: bool,
+}
+
+impl FnParts<'_> {
+    /// Removes the `.await` expressions from the body of a function that is no longer `async`.
+    fn strip_await(&mut self, errors: &mut Vec<syn::Error>) {
+        if let Some(body) = &mut self.body {
+            strip_await::strip_await(body, errors);
+        }
+    }
+
+    /// Turns an `async` function into a synchronous one that returns `impl Future` and moves the
+    /// body into an `async move` block.
+    fn desugar(&mut self, async_token: Token![async], desugar: AsyncDesugar) {
+        let span = async_token.span;
+
+        let output = match &*self.output {
+            ReturnType::Default => quote_spanned!(span=> ()),
+            ReturnType::Type(_, ty) => ty.to_token_stream(),
+        };
+        let send_bound = desugar
+            .send
+            .map(|send| quote_spanned!(send.span=> + ::core::marker::Send));
+        let capture_bound = if self.captures {
+            capture::capture_lifetimes(self.generics, self.inputs, span)
+        } else {
+            None
+        };
+        *self.output = parse_quote_spanned! {span=>
+            -> impl ::core::future::Future<Output = #output> #send_bound #capture_bound
+        };
+
+        // The desugared form is what the attribute asks for, so suggesting `async fn` is moot.
+        self.attrs
+            .push(parse_quote_spanned!(span=> #[allow(clippy::manual_async_fn)]));
+
+        if let Some(body) = &mut self.body {
+            let stmts = std::mem::take(&mut body.stmts);
+            body.stmts = vec![Stmt::Expr(
+                parse_quote_spanned!(span=> async move { #(#stmts)* }),
+                None,
+            )];
+        }
+    }
+}
+
+impl Default for QualifiersLens<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> QualifiersLens<'a> {
+    /// Returns a lens without any qualifiers, which are added with the other methods.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            visibility: None,
+            defaultness: None,
+            constness: None,
+            asyncness: None,
+            unsafety: None,
+            abi: None,
+            mutability: None,
+            autoness: None,
+            safety: None,
+            function: None,
+        }
+    }
+
+    #[must_use]
+    pub fn visibility(self, visibility: &'a mut Visibility) -> Self {
+        Self {
+            visibility: Some(visibility),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn defaultness(self, defaultness: &'a mut Option<Token![default]>) -> Self {
+        Self {
+            defaultness: Some(defaultness),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn constness(self, constness: &'a mut Option<Token![const]>) -> Self {
+        Self {
+            constness: Some(constness),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn asyncness(self, asyncness: &'a mut Option<Token![async]>) -> Self {
+        Self {
+            asyncness: Some(asyncness),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn unsafety(self, unsafety: &'a mut Option<Token![unsafe]>) -> Self {
+        Self {
+            unsafety: Some(unsafety),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn abi(self, abi: &'a mut dyn Slot<Abi>) -> Self {
+        Self {
+            abi: Some(abi),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn mutability(self, mutability: &'a mut StaticMutability) -> Self {
+        Self {
+            mutability: Some(mutability),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn autoness(self, autoness: AutoTrait<'a>) -> Self {
+        Self {
+            autoness: Some(autoness),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn safety(self, safety: &'a mut Option<kw::safe>) -> Self {
+        Self {
+            safety: Some(safety),
+            ..self
+        }
+    }
+
+    /// Adds the qualifiers of a function, along with the parts that some qualifiers check or
+    /// rewrite, like the body of a `const` function.
+    #[must_use]
+    pub fn signature(
+        self,
+        attrs: &'a mut Vec<Attribute>,
+        signature: &'a mut Signature,
+        body: Option<&'a mut Block>,
+    ) -> Self {
+        Self {
+            function: Some(FnParts {
+                attrs,
+                ident: &signature.ident,
+                generics: &mut signature.generics,
+                inputs: &mut signature.inputs,
+                output: &mut signature.output,
+                body,
+                captures: true,
+            }),
+            ..self
+                .constness(&mut signature.constness)
+                .asyncness(&mut signature.asyncness)
+                .unsafety(&mut signature.unsafety)
+                .abi(&mut signature.abi)
+        }
+    }
+
+    /// Marks the item as a member of a trait or a trait implementation, where a desugared
+    /// `impl Future` captures every lifetime already and has to keep the lifetimes of the trait.
+    #[must_use]
+    pub fn in_trait(mut self) -> Self {
+        if let Some(function) = &mut self.function {
+            function.captures = false;
+        }
+        self
+    }
+
+    /// Returns whether the item supports every qualifier that the given qualifiers change.
+    pub fn supports(&self, qualifiers: &Qualifiers) -> bool {
+        (qualifiers.visibility.is_none() || self.visibility.is_some())
+            && (qualifiers.defaultness.is_none() || self.defaultness.is_some())
+            && (qualifiers.constness.is_none() || self.constness.is_some())
+            && (qualifiers.asyncness.is_none() || self.asyncness.is_some())
+            && (qualifiers.unsafety.is_none() || self.unsafety.is_some())
+            && (qualifiers.abi.is_none() || self.abi.is_some())
+            && (qualifiers.mutability.is_none() || self.mutability.is_some())
+            && (qualifiers.autoness.is_none() || self.autoness.is_some())
+            && (qualifiers.safety.is_none() || self.safety.is_some())
+            && (qualifiers.export.is_none()
+                || matches!(&self.function, Some(function) if function.body.is_some()))
+    }
+
+    /// Applies the given qualifiers to the item.
+    pub fn apply(self, qualifiers: Qualifiers) -> syn::Result<()> {
+        let Self {
+            visibility: target_visibility,
+            defaultness: target_defaultness,
+            constness: target_constness,
+            asyncness: target_asyncness,
+            unsafety: target_unsafety,
+            abi: target_abi,
+            mutability: target_mutability,
+            autoness: target_autoness,
+            safety: target_safety,
+            function: mut target_function,
+        } = self;
+        let Qualifiers {
+            exact,
+            each: _,
+            dual: _,
+            extern_shim: _,
+            export,
+            conditions: _,
+            strip_await,
+            visibility,
+            defaultness,
+            constness,
+            asyncness,
+            mut unsafety,
+            mut abi,
+            mutability,
+            autoness,
+            mut safety,
+        } = qualifiers;
+        let exact = exact.is_some();
+
+        // `safe` and `unsafe` replace each other on items that support both.
+        if target_safety.is_some() && target_unsafety.is_some() {
+            match (&safety, &unsafety) {
+                (Some(Change::Set(safe_token)), None) => {
+                    unsafety = Some(Change::Clear(safe_token.span));
+                }
+                (None, Some(Change::Set(unsafe_token))) => {
+                    safety = Some(Change::Clear(unsafe_token.span));
+                }
+                _ => {}
+            }
+        }
+
+        // TODO: emit only a single error with every unsupported qualifier in one message?
+        let mut errors = Vec::new();
+
+        apply_change(
+            target_visibility,
+            visibility,
+            exact,
+            "visibility",
+            &mut errors,
+        );
+        apply_change(
+            target_defaultness,
+            defaultness,
+            exact,
+            "defaultness",
+            &mut errors,
+        );
+        // The body is checked before it's rewritten for `async`.
+        if let (
+            Some(_),
+            Some(Change::Set(const_qualifier)),
+            Some(FnParts {
+                body: Some(body), ..
+            }),
+        ) = (&target_constness, &constness, &target_function)
+        {
+            if const_qualifier.unchecked.is_none() {
+                const_check::check_const(body, const_qualifier.const_token, &mut errors);
+            }
+        }
+        apply_change(
+            target_constness,
+            constness.map(|constness| constness.map(|const_qualifier| const_qualifier.const_token)),
+            exact,
+            "constness",
+            &mut errors,
+        );
+        match (target_asyncness, asyncness) {
+            (
+                Some(target_asyncness),
+                Some(Change::Set(Async {
+                    async_token,
+                    desugar: Some(desugar),
+                })),
+            ) => {
+                *target_asyncness = None;
+                if let Some(function) = &mut target_function {
+                    function.desugar(async_token, desugar);
+                }
+            }
+            (Some(target_asyncness), Some(Change::Clear(_))) if strip_await.is_some() => {
+                *target_asyncness = None;
+                if let Some(function) = &mut target_function {
+                    function.strip_await(&mut errors);
+                }
+            }
+            (target_asyncness, asyncness) => apply_change(
+                target_asyncness,
+                asyncness
+                    .map(|asyncness| asyncness.map(|async_qualifier| async_qualifier.async_token)),
+                exact,
+                "asyncness",
+                &mut errors,
+            ),
+        }
+        apply_change(target_unsafety, unsafety, exact, "unsafety", &mut errors);
+        // `export` implies `extern "C"` unless another ABI is given.
+        if let Some(export) = export {
+            let span = export.export_token.span;
+            match &mut target_function {
+                Some(function) if function.body.is_some() => {
+                    match &abi {
+                        None => {
+                            abi = Some(Change::Set(Extern {
+                                abi: parse_quote_spanned!(span=> extern "C"),
+                                unchecked: None,
+                                unchecked_abi: None,
+                                header: None,
+                            }));
+                        }
+                        Some(Change::Clear(clear_span)) => errors.push(syn::Error::new(
+                            *clear_span,
+                            "exported functions need an ABI; remove `!extern`",
+                        )),
+                        Some(Change::Set(_)) => {}
+                    }
+                    let symbol = export.symbol(function.ident);
+                    function.attrs.push(export::symbol_attribute(
+                        &symbol,
+                        function.ident,
+                        span,
+                        export.unsafe_token.is_some(),
+                    ));
+                }
+                _ => errors.push(syn::Error::new(
+                    span,
+                    "only functions with bodies can be exported",
+                )),
+            }
+        }
+        if let (Some(_), Some(Change::Set(extern_qualifier)), Some(function)) =
+            (&target_abi, &abi, &target_function)
+        {
+            if extern_qualifier.unchecked.is_none() && ffi_check::is_foreign(&extern_qualifier.abi)
+            {
+                ffi_check::check_ffi(
+                    &extern_qualifier.abi,
+                    function.generics,
+                    function.inputs,
+                    function.output,
+                    &mut errors,
+                );
+            }
+        }
+        if let (
+            Some(_),
+            Some(Change::Set(Extern {
+                abi: extern_abi,
+                header: Some(header),
+                ..
+            })),
+        ) = (&target_abi, &abi)
+        {
+            match &target_function {
+                Some(function) => header::write_prototype(
+                    extern_abi,
+                    header,
+                    Prototype {
+                        attrs: function.attrs,
+                        ident: function.ident,
+                        generics: function.generics,
+                        inputs: function.inputs,
+                        output: function.output,
+                    },
+                    &mut errors,
+                ),
+                None => errors.push(syn::Error::new(
+                    header.span(),
+                    "only functions can be written to a header",
+                )),
+            }
+        }
+        apply_change(
+            target_abi,
+            abi.map(|abi| abi.map(|extern_qualifier| extern_qualifier.abi)),
+            exact,
+            "ABI",
+            &mut errors,
+        );
+        apply_change(
+            target_mutability,
+            mutability,
+            exact,
+            "mutability",
+            &mut errors,
+        );
+        apply_change(target_safety, safety, exact, "safety", &mut errors);
+
+        match (target_autoness, autoness) {
+            (Some(target_autoness), Some(Change::Set(auto_token))) => {
+                if target_autoness.check(&mut errors) {
+                    *target_autoness.auto_token = Some(auto_token);
+                }
+            }
+            (target_autoness, autoness) => apply_change(
+                target_autoness.map(|target_autoness| target_autoness.auto_token),
+                autoness,
+                exact,
+                "autoness",
+                &mut errors,
+            ),
+        }
+
+        if let Some(error) = errors.into_iter().reduce(|mut error, next| {
+            error.combine(next);
+            error
+        }) {
+            Err(error)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A part of an item that holds a single qualifier.
+pub trait Slot<T> {
+    fn set(&mut self, value: T);
+    fn clear(&mut self);
+}
+
+impl<T> Slot<T> for Option<T> {
+    fn set(&mut self, value: T) {
+        *self = Some(value);
+    }
+
+    fn clear(&mut self) {
+        *self = None;
+    }
+}
+
+/// The ABI of an `extern` block, which falls back to `extern "C"` when cleared.
+impl Slot<Abi> for Abi {
+    fn set(&mut self, value: Abi) {
+        *self = value;
+    }
+
+    fn clear(&mut self) {
+        self.name = None;
+    }
+}
+
+impl Slot<Token![mut]> for StaticMutability {
+    fn set(&mut self, value: Token![mut]) {
+        *self = StaticMutability::Mut(value);
+    }
+
+    fn clear(&mut self) {
+        *self = StaticMutability::None;
+    }
+}
+
+impl Slot<Visibility> for Visibility {
+    fn set(&mut self, value: Visibility) {
+        *self = value;
+    }
+
+    fn clear(&mut self) {
+        *self = Visibility::Inherited;
+    }
+}
+
+/// Applies a single qualifier change to its slot, if the item has one.
+///
+/// In exact mode, slots without a change are cleared instead of left alone.
+fn apply_change<S, T>(
+    target: Option<&mut S>,
+    change: Option<Change<T>>,
+    exact: bool,
+    name: &str,
+    errors: &mut Vec<syn::Error>,
+) where
+    S: Slot<T> + ?Sized,
+    T: Spanned,
+{
+    match (target, change) {
+        (Some(target), Some(Change::Set(value))) => target.set(value),
+        (Some(target), Some(Change::Clear(_))) => target.clear(),
+        (Some(target), None) if exact => target.clear(),
+        (Some(_), None) | (None, None) => {}
+        (None, Some(change)) => errors.push(syn::Error::new(
+            change.span(),
+            format!("{} qualifier not supported here", name),
+        )),
+    }
+}
+
+/// An item with qualifiers, which may be implemented for item types of other crates.
+pub trait Qualify {
+    /// Extracts an item's qualifiers.
+    fn qualify(&mut self) -> QualifiersLens<'_>;
+}
+
+//
+// Items
+//
+
+impl Qualify for Item {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        match self {
+            Self::Const(item_const) => item_const.qualify(),
+            Self::Enum(item_enum) => item_enum.qualify(),
+            Self::ExternCrate(item_extern_crate) => item_extern_crate.qualify(),
+            Self::Fn(item_fn) => item_fn.qualify(),
+            Self::ForeignMod(item_foreign_mod) => item_foreign_mod.qualify(),
+            Self::Impl(item_impl) => item_impl.qualify(),
+            Self::Macro(item_macro) => item_macro.qualify(),
+            Self::Mod(item_mod) => item_mod.qualify(),
+            Self::Static(item_static) => item_static.qualify(),
+            Self::Struct(item_struct) => item_struct.qualify(),
+            Self::Trait(item_trait) => item_trait.qualify(),
+            Self::TraitAlias(item_trait_alias) => item_trait_alias.qualify(),
+            Self::Type(item_type) => item_type.qualify(),
+            Self::Union(item_union) => item_union.qualify(),
+            Self::Use(item_use) => item_use.qualify(),
+            _ => QualifiersLens::new(),
+        }
+    }
+}
+
+impl Qualify for ItemConst {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis)
+    }
+}
+
+impl Qualify for ItemEnum {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis)
+    }
+}
+
+impl Qualify for ItemExternCrate {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis)
+    }
+}
+
+impl Qualify for ItemFn {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis).signature(
+            &mut self.attrs,
+            &mut self.sig,
+            Some(&mut self.block),
+        )
+    }
+}
+
+impl Qualify for ItemForeignMod {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .unsafety(&mut self.unsafety)
+            .abi(&mut self.abi)
+    }
+}
+
+impl Qualify for ItemImpl {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .defaultness(&mut self.defaultness)
+            .unsafety(&mut self.unsafety)
+    }
+}
+
+impl Qualify for ItemMacro {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+    }
+}
+
+impl Qualify for ItemMod {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .visibility(&mut self.vis)
+            .unsafety(&mut self.unsafety)
+    }
+}
+
+impl Qualify for ItemStatic {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .visibility(&mut self.vis)
+            .mutability(&mut self.mutability)
+    }
+}
+
+impl Qualify for ItemStruct {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis)
+    }
+}
+
+impl Qualify for ItemTrait {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .visibility(&mut self.vis)
+            .unsafety(&mut self.unsafety)
+            .autoness(AutoTrait {
+                auto_token: &mut self.auto_token,
+                generics: &self.generics,
+                supertraits: &self.supertraits,
+                items: &self.items,
+            })
+    }
+}
+
+impl Qualify for ItemTraitAlias {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis)
+    }
+}
+
+impl Qualify for ItemType {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis)
+    }
+}
+
+impl Qualify for ItemUnion {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis)
+    }
+}
+
+impl Qualify for ItemUse {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis)
+    }
+}
+
+//
+// Foreign Items
+//
+
+impl Qualify for ForeignItem {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        match self {
+            Self::Fn(item_fn) => item_fn.qualify(),
+            Self::Macro(item_macro) => item_macro.qualify(),
+            Self::Static(item_static) => item_static.qualify(),
+            Self::Type(item_type) => item_type.qualify(),
+            _ => QualifiersLens::new(),
+        }
+    }
+}
+
+impl Qualify for ForeignItemFn {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis).signature(
+            &mut self.attrs,
+            &mut self.sig,
+            None,
+        )
+    }
+}
+
+impl Qualify for ForeignItemMacro {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+    }
+}
+
+impl Qualify for ForeignItemStatic {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .visibility(&mut self.vis)
+            .mutability(&mut self.mutability)
+    }
+}
+
+impl Qualify for ForeignItemType {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis)
+    }
+}
+
+//
+// Impl Items
+//
+
+impl Qualify for ImplItem {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        match self {
+            Self::Const(item_const) => item_const.qualify(),
+            Self::Fn(item_fn) => item_fn.qualify(),
+            Self::Macro(item_macro) => item_macro.qualify(),
+            Self::Type(item_type) => item_type.qualify(),
+            _ => QualifiersLens::new(),
+        }
+    }
+}
+
+impl Qualify for ImplItemConst {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .visibility(&mut self.vis)
+            .defaultness(&mut self.defaultness)
+    }
+}
+
+impl Qualify for ImplItemFn {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .visibility(&mut self.vis)
+            .defaultness(&mut self.defaultness)
+            .signature(&mut self.attrs, &mut self.sig, Some(&mut self.block))
+    }
+}
+
+impl Qualify for ImplItemMacro {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+    }
+}
+
+impl Qualify for ImplItemType {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .visibility(&mut self.vis)
+            .defaultness(&mut self.defaultness)
+    }
+}
+
+//
+// Trait Items
+//
+
+impl Qualify for TraitItem {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        match self {
+            Self::Const(item_const) => item_const.qualify(),
+            Self::Fn(item_fn) => item_fn.qualify(),
+            Self::Macro(item_macro) => item_macro.qualify(),
+            Self::Type(item_type) => item_type.qualify(),
+            _ => QualifiersLens::new(),
+        }
+    }
+}
+
+impl Qualify for TraitItemConst {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+    }
+}
+
+impl Qualify for TraitItemFn {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .signature(&mut self.attrs, &mut self.sig, self.default.as_mut())
+            .in_trait()
+    }
+}
+
+impl Qualify for TraitItemMacro {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+    }
+}
+
+impl Qualify for TraitItemType {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+    }
+}
+
+//
+// Fields
+//
+
+impl Qualify for Field {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        // TODO: mutability?
+        QualifiersLens::new().visibility(&mut self.vis)
+    }
+}
+
+//
+// Flexible Items
+//
+
+impl Qualify for FlexibleForeignItem {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        match self {
+            Self::Fn(item_fn) => item_fn.qualify(),
+            Self::Static(item_static) => item_static.qualify(),
+        }
+    }
+}
+
+impl Qualify for QualifiedItem {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        match self {
+            Self::Const(item_const) => item_const.qualify(),
+            Self::Fn(item_fn) => item_fn.qualify(),
+            Self::Static(item_static) => item_static.qualify(),
+            Self::Type(item_type) => item_type.qualify(),
+            Self::Other(item) => item.qualify(),
+        }
+    }
+}
+
+impl Qualify for FlexibleItemConst {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .visibility(&mut self.vis)
+            .defaultness(&mut self.defaultness)
+    }
+}
+
+impl Qualify for FlexibleItemFn {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        let lens = QualifiersLens::new()
+            .visibility(&mut self.vis)
+            .defaultness(&mut self.defaultness);
+        // Only foreign functions, which have no body, can be `safe`.
+        match &mut self.body {
+            Some(body) => lens.signature(&mut self.attrs, &mut self.sig, Some(body)),
+            None => lens
+                .signature(&mut self.attrs, &mut self.sig, None)
+                .safety(&mut self.safety),
+        }
+    }
+}
+
+impl Qualify for FlexibleItemStatic {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        let lens = QualifiersLens::new()
+            .visibility(&mut self.vis)
+            .mutability(&mut self.mutability);
+        // Only foreign statics, which have no body, can be `safe` or `unsafe`.
+        if self.body.is_none() {
+            lens.unsafety(&mut self.unsafety).safety(&mut self.safety)
+        } else {
+            lens
+        }
+    }
+}
+
+impl Qualify for FlexibleItemType {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new()
+            .visibility(&mut self.vis)
+            .defaultness(&mut self.defaultness)
+    }
+}
@@ -0,0 +1,110 @@
+//! The parser and the qualifier lens behind [`qualifier_attr`], for procedural macros that apply
+//! qualifiers themselves.
+//!
+//! [`Qualifiers`] parses the same lists of qualifiers as `#[qualifiers(...)]` and prints them
+//! back, and [`apply_qualifiers`] applies them to an item:
+//!
+//! ```
+//! # #[cfg(feature = "full")]
+//! # fn main() -> syn::Result<()> {
+//! use qualifier_attr_core::{apply_qualifiers, Qualifiers};
+//!
+//! let qualifiers: Qualifiers = syn::parse_quote!(pub, const);
+//! let mut item: syn::Item = syn::parse_quote!(fn answer() -> u32 { 42 });
+//! apply_qualifiers(&mut item, &qualifiers)?;
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "full"))]
+//! # fn main() {}
+//! ```
+//!
+//! Item types of other crates can implement [`Qualify`] to tell where their qualifiers are, and
+//! the attributes of `qualifier_attr` are available as functions in [`expand`].
+//!
+//! [`qualifier_attr`]: https://docs.rs/qualifier_attr
+
+// Without `full`, the modes that only the `syn` backend applies are still parsed, so that they
+// can be reported.
+#![cfg_attr(not(feature = "full"), allow(dead_code))]
+
+extern crate proc_macro2 as pm2;
+
+#[cfg(feature = "full")]
+use syn::Item;
+
+#[cfg(feature = "full")]
+pub use crate::helper::{AutoTrait, QualifiersLens, Qualify, Slot};
+pub use crate::parse::{Change, Qualifier, Qualifiers};
+#[cfg(feature = "full")]
+pub use crate::parse::{
+    FieldQualifiers, FlexibleForeignItem, FlexibleItemConst, FlexibleItemFn, FlexibleItemStatic,
+    FlexibleItemType, MemberQualifiers, QualifiedItem,
+};
+
+mod abi;
+#[cfg(feature = "full")]
+mod capture;
+mod cfg;
+#[cfg(feature = "full")]
+mod const_check;
+#[cfg(feature = "full")]
+mod dual;
+#[cfg(feature = "full")]
+mod each;
+pub mod expand;
+#[cfg(feature = "full")]
+mod export;
+#[cfg(feature = "full")]
+mod ffi_check;
+#[cfg(feature = "full")]
+mod fields;
+#[cfg(feature = "full")]
+mod header;
+#[cfg(feature = "full")]
+mod helper;
+#[cfg(feature = "full")]
+mod markers;
+#[cfg(feature = "full")]
+mod members;
+/// The syntax of qualifiers, and of the items that they are applied to.
+pub mod parse;
+#[cfg(feature = "full")]
+mod shim;
+#[cfg(feature = "full")]
+mod strip_await;
+// Without `full`, the token backend is the only backend, whether `token_backend` is on or not.
+#[cfg(any(feature = "token_backend", not(feature = "full")))]
+mod tokens;
+
+/// Applies qualifiers to an item, like `#[qualifiers(...)]` does.
+///
+/// Conditional qualifiers have to be resolved with [`Qualifiers::resolve`] first, and `dual` and
+/// `extern_shim` are reported, since they emit items of their own. Use
+/// [`expand::qualifiers`] for those.
+#[cfg(feature = "full")]
+pub fn apply_qualifiers(item: &mut Item, qualifiers: &Qualifiers) -> syn::Result<()> {
+    let mut qualifiers = qualifiers.clone();
+    if let Some(condition) = qualifiers.conditions.first() {
+        return Err(syn::Error::new(
+            condition.qualifier.span(),
+            "conditional qualifiers have to be resolved with `Qualifiers::resolve` first",
+        ));
+    }
+    if let Some(dual) = &qualifiers.dual {
+        return Err(syn::Error::new(
+            dual.dual_token.span,
+            "`dual` emits a twin of the item, which `apply_qualifiers` can't",
+        ));
+    }
+    if let Some(extern_shim) = &qualifiers.extern_shim {
+        return Err(syn::Error::new(
+            extern_shim.extern_shim_token.span,
+            "`extern_shim` emits a wrapper of the item, which `apply_qualifiers` can't",
+        ));
+    }
+
+    match qualifiers.each.take() {
+        Some(each) => each::qualify_members(item, &each, None, qualifiers),
+        None => item.qualify().apply(qualifiers),
+    }
+}
@@ -0,0 +1,181 @@
+use quote::quote;
+use syn::{
+    parse::Parser, parse_quote, punctuated::Punctuated, spanned::Spanned, Attribute, Item, Meta,
+    MetaList, Token,
+};
+
+use crate::{
+    members::{foreign_item_attrs, impl_item_attrs, trait_item_attrs},
+    parse::{push_predicate, FlexibleForeignItem, Qualifiers},
+};
+
+/// A `#[qualify(...)]` helper attribute.
+#[derive(Clone)]
+pub(crate) struct Marker {
+    /// The `cfg` predicate of the surrounding `cfg_attr`, if any.
+    pub predicate: Option<Meta>,
+    pub qualifiers: Qualifiers,
+}
+
+impl Marker {
+    /// Returns the qualifiers of the marker when exactly the predicates for which `holds`
+    /// returns `true` hold, or `None` if its `cfg_attr` doesn't apply.
+    pub fn resolve(&self, holds: &dyn Fn(&Meta) -> bool) -> syn::Result<Option<Qualifiers>> {
+        match &self.predicate {
+            Some(predicate) if !holds(predicate) => Ok(None),
+            _ => self.qualifiers.resolve(holds).map(Some),
+        }
+    }
+}
+
+/// Returns the distinct `cfg` predicates of the markers, including those of their conditional
+/// qualifiers, after the given ones.
+pub(crate) fn predicates<'a>(
+    mut predicates: Vec<Meta>,
+    markers: impl IntoIterator<Item = &'a Marker>,
+) -> Vec<Meta> {
+    for marker in markers {
+        if let Some(predicate) = &marker.predicate {
+            push_predicate(&mut predicates, predicate);
+        }
+        for predicate in marker.qualifiers.predicates() {
+            push_predicate(&mut predicates, &predicate);
+        }
+    }
+    predicates
+}
+
+/// Removes every `#[qualify(...)]` helper attribute from the attributes and returns them in
+/// order, including the ones nested in `#[cfg_attr(...)]`.
+///
+/// Attribute macros see their input before `cfg_attr` is expanded, so the predicates of the
+/// markers can't be evaluated here. Instead, the item is expanded into `cfg`-gated copies like
+/// it is for conditional qualifiers.
+pub(crate) fn take_markers(attrs: &mut Vec<Attribute>) -> syn::Result<Vec<Marker>> {
+    let mut markers = Vec::new();
+    let mut errors = Vec::new();
+
+    for mut attr in std::mem::take(attrs) {
+        match take_from_meta(&attr.meta, None, &mut markers) {
+            Ok(Some(meta)) => {
+                attr.meta = meta;
+                attrs.push(attr);
+            }
+            Ok(None) => {}
+            Err(error) => {
+                errors.push(error);
+                attrs.push(attr);
+            }
+        }
+    }
+
+    if let Some(error) = errors.into_iter().reduce(|mut error, next| {
+        error.combine(next);
+        error
+    }) {
+        Err(error)
+    } else {
+        Ok(markers)
+    }
+}
+
+/// Removes every `#[qualify(...)]` helper attribute from the fields or members of an item,
+/// including invalid ones, so that the item can be emitted as it is next to an error.
+pub(crate) fn strip_markers(item: &mut Item) {
+    match item {
+        Item::Struct(item_struct) => {
+            for field in &mut item_struct.fields {
+                strip_from_attrs(&mut field.attrs);
+            }
+        }
+        Item::Union(item_union) => {
+            for field in &mut item_union.fields.named {
+                strip_from_attrs(&mut field.attrs);
+            }
+        }
+        Item::Impl(item_impl) => {
+            for member in &mut item_impl.items {
+                if let Some(attrs) = impl_item_attrs(member) {
+                    strip_from_attrs(attrs);
+                }
+            }
+        }
+        Item::Trait(item_trait) => {
+            for member in &mut item_trait.items {
+                if let Some(attrs) = trait_item_attrs(member) {
+                    strip_from_attrs(attrs);
+                }
+            }
+        }
+        Item::ForeignMod(item_foreign_mod) => {
+            for member in &mut item_foreign_mod.items {
+                if let Some(mut flexible) = FlexibleForeignItem::from_foreign_item(member) {
+                    strip_from_attrs(flexible.attrs_mut());
+                    *member = flexible.into_foreign_item();
+                } else if let Some(attrs) = foreign_item_attrs(member) {
+                    strip_from_attrs(attrs);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn strip_from_attrs(attrs: &mut Vec<Attribute>) {
+    // Valid markers are removed along with the ones nested in `cfg_attr`, while invalid ones are
+    // left in place.
+    let _ = take_markers(attrs);
+    attrs.retain(|attr| !attr.path().is_ident("qualify"));
+}
+
+/// Collects the markers in an attribute and returns what remains of it, if anything.
+fn take_from_meta(
+    meta: &Meta,
+    predicate: Option<&Meta>,
+    markers: &mut Vec<Marker>,
+) -> syn::Result<Option<Meta>> {
+    match meta {
+        Meta::List(list) if list.path.is_ident("qualify") => {
+            let qualifiers = syn::parse2::<Qualifiers>(list.tokens.clone())?;
+            qualifiers.check_item_only()?;
+            markers.push(Marker {
+                predicate: predicate.cloned(),
+                qualifiers,
+            });
+            Ok(None)
+        }
+        Meta::Path(path) if path.is_ident("qualify") => {
+            Err(syn::Error::new(path.span(), "expected `qualify(...)`"))
+        }
+        Meta::List(list) if list.path.is_ident("cfg_attr") => {
+            let args =
+                Punctuated::<Meta, Token![,]>::parse_terminated.parse2(list.tokens.clone())?;
+            let cfg_predicate = match args.first() {
+                Some(cfg_predicate) if args.len() > 1 => cfg_predicate,
+                _ => return Ok(Some(meta.clone())),
+            };
+            let nested_predicate: Meta = match predicate {
+                Some(predicate) => parse_quote!(all(#predicate, #cfg_predicate)),
+                None => cfg_predicate.clone(),
+            };
+
+            let mut remaining = Punctuated::<Meta, Token![,]>::new();
+            for nested in args.iter().skip(1) {
+                if let Some(nested) = take_from_meta(nested, Some(&nested_predicate), markers)? {
+                    remaining.push(nested);
+                }
+            }
+
+            if remaining.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(Meta::List(MetaList {
+                    path: list.path.clone(),
+                    delimiter: list.delimiter.clone(),
+                    tokens: quote!(#cfg_predicate, #remaining),
+                })))
+            }
+        }
+        _ => Ok(Some(meta.clone())),
+    }
+}
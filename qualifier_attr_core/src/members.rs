@@ -0,0 +1,380 @@
+use quote::quote;
+use syn::{
+    ext::IdentExt, spanned::Spanned, Attribute, ForeignItem, Ident, ImplItem, Item, Meta, TraitItem,
+};
+
+use crate::{
+    cfg::{self, Combination},
+    each::{describe_impl_item, describe_trait_item},
+    header,
+    helper::Qualify,
+    markers::{self, take_markers, Marker},
+    parse::{
+        push_predicate, Async, Change, FlexibleForeignItem, MemberKind, MemberQualifiers,
+        Qualifiers,
+    },
+};
+
+/// Applies qualifiers to the members of an implementation by name, and returns the errors of
+/// single combinations of predicates as gated items to emit next to it.
+pub(crate) fn qualify_impl_members(
+    item: &mut Item,
+    member_qualifiers: &MemberQualifiers,
+) -> syn::Result<pm2::TokenStream> {
+    match item {
+        Item::Impl(item_impl) => {
+            let in_trait = item_impl.trait_.is_some();
+            qualify_named(
+                &mut item_impl.items,
+                describe_impl_item,
+                in_trait,
+                member_qualifiers,
+            )
+        }
+        _ => Err(syn::Error::new_spanned(
+            item,
+            "`impl_qualifiers` is only supported on implementations",
+        )),
+    }
+}
+
+/// Applies qualifiers to the members of a trait by name, and returns the errors of single
+/// combinations of predicates as gated items to emit next to it.
+pub(crate) fn qualify_trait_members(
+    item: &mut Item,
+    member_qualifiers: &MemberQualifiers,
+) -> syn::Result<pm2::TokenStream> {
+    match item {
+        Item::Trait(item_trait) => qualify_named(
+            &mut item_trait.items,
+            describe_trait_item,
+            true,
+            member_qualifiers,
+        ),
+        _ => Err(syn::Error::new_spanned(
+            item,
+            "`trait_qualifiers` is only supported on traits",
+        )),
+    }
+}
+
+fn qualify_named<T: Member>(
+    items: &mut Vec<T>,
+    describe: fn(&T) -> (Option<MemberKind>, Option<Ident>),
+    in_trait: bool,
+    MemberQualifiers(member_qualifiers): &MemberQualifiers,
+) -> syn::Result<pm2::TokenStream> {
+    // NOTE: Associated types live in a different namespace than associated
+    // constants and functions, so a name may refer to more than one member.
+    // Every member with the name is qualified.
+
+    let mut errors = Vec::new();
+    // The qualifiers of each member, in order.
+    let mut sources = vec![Vec::new(); items.len()];
+    for (name, qualifiers) in member_qualifiers {
+        let name_string = name.unraw().to_string();
+        let mut found = false;
+        for (i, item) in items.iter().enumerate() {
+            let (_, item_name) = describe(item);
+            if item_name.map_or(false, |item_name| item_name.unraw() == name_string) {
+                found = true;
+                sources[i].push(qualifiers);
+            }
+        }
+        if !found {
+            errors.push(syn::Error::new(
+                name.span(),
+                format!("unknown member `{}`", name),
+            ));
+        }
+    }
+    if let Some(error) = errors.into_iter().reduce(|mut error, next| {
+        error.combine(next);
+        error
+    }) {
+        return Err(error);
+    }
+
+    expand_members(
+        items,
+        |i| {
+            let mut predicates = Vec::new();
+            for qualifiers in &sources[i] {
+                for predicate in qualifiers.predicates() {
+                    push_predicate(&mut predicates, &predicate);
+                }
+            }
+            predicates
+        },
+        |i, member, predicates, combination| {
+            let holds = |predicate: &Meta| combination.holds(predicate);
+            let mut errors = Vec::new();
+            for qualifiers in &sources[i] {
+                let result = qualifiers
+                    .resolve(&holds)
+                    .and_then(|qualifiers| {
+                        // Only the combination that is built writes to headers.
+                        header::skip_unless_built(qualifiers, predicates, &holds)
+                    })
+                    .and_then(|qualifiers| member.apply(qualifiers, in_trait));
+                if let Err(error) = result {
+                    errors.push(error);
+                }
+            }
+            combine(errors)
+        },
+    )
+}
+
+/// A member of an implementation, trait, or `extern` block.
+trait Member: Qualify + Clone {
+    /// Calls `f` with the attributes of the member, if it has any.
+    fn with_attrs<R>(&mut self, f: impl FnOnce(&mut Vec<Attribute>) -> R) -> Option<R>;
+
+    /// Applies qualifiers to the member.
+    fn apply(&mut self, qualifiers: Qualifiers, in_trait: bool) -> syn::Result<()> {
+        let lens = match in_trait {
+            true => self.qualify().in_trait(),
+            false => self.qualify(),
+        };
+        lens.apply(qualifiers)
+    }
+}
+
+impl Member for ImplItem {
+    fn with_attrs<R>(&mut self, f: impl FnOnce(&mut Vec<Attribute>) -> R) -> Option<R> {
+        impl_item_attrs(self).map(f)
+    }
+}
+
+impl Member for TraitItem {
+    fn with_attrs<R>(&mut self, f: impl FnOnce(&mut Vec<Attribute>) -> R) -> Option<R> {
+        trait_item_attrs(self).map(f)
+    }
+}
+
+// `syn` can't represent `safe` foreign items, so functions and statics are handled as flexible
+// items instead.
+impl Member for ForeignItem {
+    fn with_attrs<R>(&mut self, f: impl FnOnce(&mut Vec<Attribute>) -> R) -> Option<R> {
+        match FlexibleForeignItem::from_foreign_item(self) {
+            Some(mut flexible) => {
+                let result = f(flexible.attrs_mut());
+                *self = flexible.into_foreign_item();
+                Some(result)
+            }
+            None => foreign_item_attrs(self).map(f),
+        }
+    }
+
+    fn apply(&mut self, qualifiers: Qualifiers, _in_trait: bool) -> syn::Result<()> {
+        match FlexibleForeignItem::from_foreign_item(self) {
+            Some(mut flexible) => {
+                let result = flexible.qualify().apply(qualifiers);
+                *self = flexible.into_foreign_item();
+                result
+            }
+            None => self.qualify().apply(qualifiers),
+        }
+    }
+}
+
+/// Expands every member into `cfg`-gated copies, one for every combination of the predicates
+/// that `predicates` returns for it, and returns the errors of single combinations as gated
+/// items.
+///
+/// `qualify` is called with the index of the member, a copy of it, its predicates, and the
+/// combination.
+fn expand_members<T: Member>(
+    members: &mut Vec<T>,
+    predicates: impl Fn(usize) -> Vec<Meta>,
+    mut qualify: impl FnMut(usize, &mut T, &[Meta], &Combination) -> syn::Result<()>,
+) -> syn::Result<pm2::TokenStream> {
+    let mut errors = pm2::TokenStream::new();
+    let mut expanded = Vec::new();
+    for (i, member) in members.iter().enumerate() {
+        let predicates = predicates(i);
+        expanded.extend(cfg::expand_element(
+            member,
+            &predicates,
+            |member, gate| {
+                member.with_attrs(|attrs| attrs.insert(0, gate));
+            },
+            |member, combination| qualify(i, member, &predicates, combination),
+            &mut errors,
+        )?);
+    }
+    *members = expanded;
+    Ok(errors)
+}
+
+/// Removes the `#[qualify(...)]` helper attributes from the members of an implementation, trait,
+/// or `extern` block and returns them, in the order of the members.
+pub(crate) fn take_member_markers(item: &mut Item) -> syn::Result<Vec<Vec<Marker>>> {
+    fn take<T: Member>(members: &mut [T]) -> syn::Result<Vec<Vec<Marker>>> {
+        let mut errors = Vec::new();
+        let mut markers = Vec::new();
+        for member in members {
+            match member.with_attrs(take_markers) {
+                Some(Ok(member_markers)) => markers.push(member_markers),
+                Some(Err(error)) => {
+                    errors.push(error);
+                    markers.push(Vec::new());
+                }
+                None => markers.push(Vec::new()),
+            }
+        }
+        combine(errors).map(|()| markers)
+    }
+
+    match item {
+        Item::Impl(item_impl) => take(&mut item_impl.items),
+        Item::Trait(item_trait) => take(&mut item_trait.items),
+        Item::ForeignMod(item_foreign_mod) => take(&mut item_foreign_mod.items),
+        _ => Err(unsupported(item)),
+    }
+}
+
+/// Applies the `#[qualify(...)]` helper attributes that were taken from the members of an
+/// implementation, trait, or `extern` block, and returns the errors of single combinations of
+/// predicates as gated items to emit next to it.
+///
+/// Members with helper attributes in `cfg_attr` are expanded into `cfg`-gated copies one by one,
+/// rather than copying the whole item. Since the members are qualified together, the ones that
+/// are made `async` are also checked for a common executor bound: if any of their futures is
+/// `Send` with `async(send)`, all of them have to be, whenever their conditions hold together.
+pub(crate) fn qualify_marked_members(
+    item: &mut Item,
+    markers: &[Vec<Marker>],
+) -> syn::Result<pm2::TokenStream> {
+    match item {
+        Item::Impl(item_impl) => {
+            let in_trait = item_impl.trait_.is_some();
+            qualify_marked(&mut item_impl.items, markers, in_trait)
+        }
+        Item::Trait(item_trait) => qualify_marked(&mut item_trait.items, markers, true),
+        Item::ForeignMod(item_foreign_mod) => {
+            qualify_marked(&mut item_foreign_mod.items, markers, false)
+        }
+        _ => Err(unsupported(item)),
+    }
+}
+
+fn qualify_marked<T: Member>(
+    members: &mut Vec<T>,
+    markers: &[Vec<Marker>],
+    in_trait: bool,
+) -> syn::Result<pm2::TokenStream> {
+    let no_markers = Vec::new();
+    let markers_of = |i: usize| markers.get(i).unwrap_or(&no_markers);
+    // The `async` tokens of the members that are made `async`, along with the combinations that
+    // they are made `async` in and whether their futures are `Send`.
+    let mut asyncs = Vec::new();
+
+    let mut errors = expand_members(
+        members,
+        |i| markers::predicates(Vec::new(), markers_of(i)),
+        |i, member, predicates, combination| {
+            let holds = |predicate: &Meta| combination.holds(predicate);
+            let mut errors = Vec::new();
+            for marker in markers_of(i) {
+                let qualifiers = match marker.resolve(&holds).and_then(|qualifiers| {
+                    qualifiers
+                        .map(|qualifiers| header::skip_unless_built(qualifiers, predicates, &holds))
+                        .transpose()
+                }) {
+                    Ok(Some(qualifiers)) => qualifiers,
+                    Ok(None) => continue,
+                    Err(error) => {
+                        errors.push(error);
+                        continue;
+                    }
+                };
+                if let Some(Change::Set(Async {
+                    async_token,
+                    desugar,
+                })) = &qualifiers.asyncness
+                {
+                    let send = desugar
+                        .as_ref()
+                        .map_or(false, |desugar| desugar.send.is_some());
+                    asyncs.push((async_token.span, combination.gate(), send));
+                }
+                if let Err(error) = member.apply(qualifiers, in_trait) {
+                    errors.push(error);
+                }
+            }
+            combine(errors)
+        },
+    )?;
+
+    let sends: Vec<_> = asyncs
+        .iter()
+        .filter(|(_, _, send)| *send)
+        .map(|(_, gate, _)| gate)
+        .collect();
+    if !sends.is_empty() {
+        for (span, gate, send) in &asyncs {
+            if !send {
+                let error = syn::Error::new(
+                    *span,
+                    "the futures of the other members are `Send`, so this one should be too; use `async(send)`",
+                )
+                .into_compile_error();
+                errors.extend(quote! {
+                    #[cfg(all(#gate, any(#(#sends),*)))]
+                    #error
+                });
+            }
+        }
+    }
+    Ok(errors)
+}
+
+fn unsupported(item: &Item) -> syn::Error {
+    syn::Error::new(
+        item.span(),
+        "`qualify_members` is only supported on implementations, traits, and `extern` blocks",
+    )
+}
+
+fn combine(errors: Vec<syn::Error>) -> syn::Result<()> {
+    if let Some(error) = errors.into_iter().reduce(|mut error, next| {
+        error.combine(next);
+        error
+    }) {
+        Err(error)
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn impl_item_attrs(item: &mut ImplItem) -> Option<&mut Vec<Attribute>> {
+    match item {
+        ImplItem::Const(item) => Some(&mut item.attrs),
+        ImplItem::Fn(item) => Some(&mut item.attrs),
+        ImplItem::Macro(item) => Some(&mut item.attrs),
+        ImplItem::Type(item) => Some(&mut item.attrs),
+        _ => None,
+    }
+}
+
+pub(crate) fn trait_item_attrs(item: &mut TraitItem) -> Option<&mut Vec<Attribute>> {
+    match item {
+        TraitItem::Const(item) => Some(&mut item.attrs),
+        TraitItem::Fn(item) => Some(&mut item.attrs),
+        TraitItem::Macro(item) => Some(&mut item.attrs),
+        TraitItem::Type(item) => Some(&mut item.attrs),
+        _ => None,
+    }
+}
+
+pub(crate) fn foreign_item_attrs(item: &mut ForeignItem) -> Option<&mut Vec<Attribute>> {
+    match item {
+        ForeignItem::Fn(item) => Some(&mut item.attrs),
+        ForeignItem::Macro(item) => Some(&mut item.attrs),
+        ForeignItem::Static(item) => Some(&mut item.attrs),
+        ForeignItem::Type(item) => Some(&mut item.attrs),
+        _ => None,
+    }
+}
@@ -1,165 +1,114 @@
 use quote::{ToTokens, TokenStreamExt};
 
+use pm2::Delimiter;
 use syn::{
     braced,
     ext::IdentExt,
-    parenthesized,
     parse::{Parse, ParseStream},
-    spanned::Spanned,
 };
 use syn::{
-    punctuated::Punctuated, token::Brace, Abi, AttrStyle, Attribute, Block, Expr, Generics, Ident,
-    Signature, StaticMutability, Token, Type, TypeParamBound, Visibility,
+    buffer::Cursor, punctuated::Punctuated, token::Brace, AttrStyle, Attribute, Block, Expr,
+    Generics, Ident, Item, Signature, StaticMutability, Token, Type, TypeParamBound, Visibility,
 };
 
-use syn::{ForeignItemFn, ForeignItemStatic, ForeignItemType};
+use syn::{ForeignItem, ForeignItemFn, ForeignItemStatic, ForeignItemType};
 use syn::{ImplItemConst, ImplItemFn, ImplItemType};
 use syn::{ItemConst, ItemFn, ItemStatic, ItemType};
 use syn::{TraitItemConst, TraitItemFn, TraitItemType};
 
-/// A qualifier.
-#[derive(Clone)]
-pub enum Qualifier {
-    Visibility(Visibility),
-    Defaultness(Token![default]),
-    Constness(Token![const]),
-    Asyncness(Token![async]),
-    Unsafety(Token![unsafe]),
-    Abi(Abi),
+use super::kw;
+
+/// The kind of an item, as far as how it is parsed is concerned.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ItemClass {
+    Const,
+    Fn,
+    Static,
+    Type,
+    Other,
 }
 
-impl Parse for Qualifier {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        if input.peek(Token![pub]) {
-            input.parse().map(Self::Visibility)
-        } else if input.peek(Token![default]) {
-            input.parse().map(Self::Defaultness)
-        } else if input.peek(Token![const]) {
-            input.parse().map(Self::Constness)
-        } else if input.peek(Token![async]) {
-            input.parse().map(Self::Asyncness)
-        } else if input.peek(Token![unsafe]) {
-            input.parse().map(Self::Unsafety)
-        } else if input.peek(Token![extern]) {
-            input.parse().map(Self::Abi)
-        } else {
-            Err(syn::Error::new(input.span(), "expected a qualifier"))
+impl ItemClass {
+    /// Classifies the item at the cursor by skipping its attributes, visibility, and qualifiers
+    /// and looking at its keyword, so that it can be parsed exactly once.
+    pub fn at(mut cursor: Cursor) -> Self {
+        while let Some((pound, next)) = cursor.punct() {
+            match next.group(Delimiter::Bracket) {
+                Some((_, _, next)) if pound.as_char() == '#' => cursor = next,
+                _ => return Self::Other,
+            }
         }
-    }
-}
 
-/// A set of qualifiers.
-#[derive(Clone)]
-pub struct Qualifiers {
-    pub visibility: Option<Visibility>,
-    pub defaultness: Option<Token![default]>,
-    pub constness: Option<Token![const]>,
-    pub asyncness: Option<Token![async]>,
-    pub unsafety: Option<Token![unsafe]>,
-    pub abi: Option<Abi>,
-}
+        if let Some((pub_token, next)) = cursor.ident() {
+            if pub_token == "pub" {
+                cursor = match next.group(Delimiter::Parenthesis) {
+                    Some((_, _, next)) => next,
+                    None => next,
+                };
+            }
+        }
 
-impl Parse for Qualifiers {
-    // implement for parsing a list of qualifiers NOT enclosed in square brackets
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let mut visibility = None;
-        let mut defaultness = None;
-        let mut constness = None;
-        let mut asyncness = None;
-        let mut unsafety = None;
-        let mut abi = None;
-
-        while !input.is_empty() {
-            let qualifier = input.parse::<Qualifier>()?;
-            match qualifier {
-                Qualifier::Visibility(visibility_token) => {
-                    if visibility.is_some() {
-                        return Err(syn::Error::new(
-                            visibility_token.span(),
-                            "visibility already specified",
-                        ));
-                    }
-                    visibility = Some(visibility_token);
+        while let Some((keyword, next)) = cursor.ident() {
+            match &*keyword.to_string() {
+                "async" | "default" | "safe" | "unsafe" => cursor = next,
+                "extern" => {
+                    cursor = match next.literal() {
+                        Some((_, next)) => next,
+                        None => next,
+                    };
                 }
-                Qualifier::Defaultness(defaultness_token) => {
-                    if defaultness.is_some() {
-                        return Err(syn::Error::new(
-                            defaultness_token.span(),
-                            "defaultness already specified",
-                        ));
+                // `const` is a qualifier of functions, and the keyword of constants.
+                "const" => match next.ident() {
+                    Some((keyword, _))
+                        if ["async", "extern", "fn", "safe", "unsafe"]
+                            .iter()
+                            .any(|qualifier| keyword == qualifier) =>
+                    {
+                        cursor = next;
                     }
-                    defaultness = Some(defaultness_token);
-                }
-                Qualifier::Constness(constness_token) => {
-                    if constness.is_some() {
-                        return Err(syn::Error::new(
-                            constness_token.span(),
-                            "constness already specified",
-                        ));
-                    }
-                    constness = Some(constness_token);
-                }
-                Qualifier::Asyncness(asyncness_token) => {
-                    if asyncness.is_some() {
-                        return Err(syn::Error::new(
-                            asyncness_token.span(),
-                            "asyncness already specified",
-                        ));
-                    }
-                    asyncness = Some(asyncness_token);
-                }
-                Qualifier::Unsafety(unsafety_token) => {
-                    if unsafety.is_some() {
-                        return Err(syn::Error::new(
-                            unsafety_token.span(),
-                            "unsafety already specified",
-                        ));
-                    }
-                    unsafety = Some(unsafety_token);
-                }
-                Qualifier::Abi(abi_token) => {
-                    if abi.is_some() {
-                        return Err(syn::Error::new(abi_token.span(), "ABI already specified"));
-                    }
-                    abi = Some(abi_token);
-                }
-            }
-            if !input.is_empty() {
-                input.parse::<Token![,]>()?;
+                    _ => return Self::Const,
+                },
+                "fn" => return Self::Fn,
+                "static" => return Self::Static,
+                "type" => return Self::Type,
+                _ => return Self::Other,
             }
         }
-
-        Ok(Self {
-            visibility,
-            defaultness,
-            constness,
-            asyncness,
-            unsafety,
-            abi,
-        })
+        Self::Other
     }
 }
 
-/// A list of field names and the qualifiers to apply to them.
+/// An item parsed as a flexible item where possible, which is what `#[qualifiers]` applies to.
 #[derive(Clone)]
-pub struct FieldQualifiers(pub Vec<(Ident, Qualifiers)>);
+pub enum QualifiedItem {
+    Const(FlexibleItemConst),
+    Fn(FlexibleItemFn),
+    Static(FlexibleItemStatic),
+    Type(FlexibleItemType),
+    Other(Item),
+}
 
-impl Parse for FieldQualifiers {
+impl Parse for QualifiedItem {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let mut list = Vec::new();
-
-        while !input.is_empty() {
-            let name = input.parse::<Ident>()?;
-            let content;
-            let _paren_token = parenthesized!(content in input);
-            let qualifiers = content.parse::<Qualifiers>()?;
-            list.push((name, qualifiers));
-            if !input.is_empty() {
-                input.parse::<Token![,]>()?;
-            }
+        match ItemClass::at(input.cursor()) {
+            ItemClass::Const => input.parse().map(Self::Const),
+            ItemClass::Fn => input.parse().map(Self::Fn),
+            ItemClass::Static => input.parse().map(Self::Static),
+            ItemClass::Type => input.parse().map(Self::Type),
+            ItemClass::Other => input.parse().map(Self::Other),
         }
+    }
+}
 
-        Ok(Self(list))
+impl ToTokens for QualifiedItem {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        match self {
+            Self::Const(item_const) => item_const.to_tokens(tokens),
+            Self::Fn(item_fn) => item_fn.to_tokens(tokens),
+            Self::Static(item_static) => item_static.to_tokens(tokens),
+            Self::Type(item_type) => item_type.to_tokens(tokens),
+            Self::Other(item) => item.to_tokens(tokens),
+        }
     }
 }
 
@@ -278,11 +227,14 @@ impl From<TraitItemConst> for FlexibleItemConst {
 }
 
 /// A combination of [`ItemFn`], [`ForeignItemFn`], [`ImplItemFn`], and [`TraitItemFn`].
+///
+/// Unlike [`ForeignItemFn`], this can also represent `safe` foreign functions.
 #[derive(Clone)]
 pub struct FlexibleItemFn {
     pub attrs: Vec<Attribute>,
     pub vis: Visibility,
     pub defaultness: Option<Token![default]>,
+    pub safety: Option<kw::safe>,
     pub sig: Signature,
     pub body: Option<Block>,
     pub semi_token: Option<Token![;]>,
@@ -293,6 +245,7 @@ impl Parse for FlexibleItemFn {
         let mut attrs = input.call(Attribute::parse_outer)?;
         let vis = input.parse()?;
         let defaultness = input.parse()?;
+        let safety = input.parse()?;
         let sig = input.parse()?;
 
         let lookahead = input.lookahead1();
@@ -313,6 +266,7 @@ impl Parse for FlexibleItemFn {
             attrs,
             vis,
             defaultness,
+            safety,
             sig,
             body: brace_token.map(|brace_token| Block { brace_token, stmts }),
             semi_token,
@@ -329,6 +283,7 @@ impl ToTokens for FlexibleItemFn {
         );
         self.vis.to_tokens(tokens);
         self.defaultness.to_tokens(tokens);
+        self.safety.to_tokens(tokens);
         self.sig.to_tokens(tokens);
         if let Some(body) = &self.body {
             body.brace_token.surround(tokens, |tokens| {
@@ -350,6 +305,7 @@ impl From<ItemFn> for FlexibleItemFn {
             attrs: item_fn.attrs,
             vis: item_fn.vis,
             defaultness: None,
+            safety: None,
             sig: item_fn.sig,
             body: Some(*item_fn.block),
             semi_token: None,
@@ -363,6 +319,7 @@ impl From<ForeignItemFn> for FlexibleItemFn {
             attrs: item_fn.attrs,
             vis: item_fn.vis,
             defaultness: None,
+            safety: None,
             sig: item_fn.sig,
             body: None,
             semi_token: Some(item_fn.semi_token),
@@ -376,6 +333,7 @@ impl From<ImplItemFn> for FlexibleItemFn {
             attrs: item_fn.attrs,
             vis: item_fn.vis,
             defaultness: item_fn.defaultness,
+            safety: None,
             sig: item_fn.sig,
             body: Some(item_fn.block),
             semi_token: None,
@@ -389,6 +347,7 @@ impl From<TraitItemFn> for FlexibleItemFn {
             attrs: item_fn.attrs,
             vis: Visibility::Inherited,
             defaultness: None,
+            safety: None,
             sig: item_fn.sig,
             body: item_fn.default,
             semi_token: item_fn.semi_token,
@@ -397,10 +356,14 @@ impl From<TraitItemFn> for FlexibleItemFn {
 }
 
 /// A combination of [`ItemStatic`] and [`ForeignItemStatic`].
+///
+/// Unlike [`ForeignItemStatic`], this can also represent `safe` and `unsafe` foreign statics.
 #[derive(Clone)]
 pub struct FlexibleItemStatic {
     pub attrs: Vec<Attribute>,
     pub vis: Visibility,
+    pub unsafety: Option<Token![unsafe]>,
+    pub safety: Option<kw::safe>,
     pub static_token: Token![static],
     pub mutability: StaticMutability,
     pub ident: Ident,
@@ -415,6 +378,8 @@ impl Parse for FlexibleItemStatic {
         Ok(Self {
             attrs: input.call(Attribute::parse_outer)?,
             vis: input.parse()?,
+            unsafety: input.parse()?,
+            safety: input.parse()?,
             static_token: input.parse()?,
             mutability: input.parse()?,
             ident: input.parse()?,
@@ -436,6 +401,8 @@ impl ToTokens for FlexibleItemStatic {
     fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
         tokens.append_all(&self.attrs);
         self.vis.to_tokens(tokens);
+        self.unsafety.to_tokens(tokens);
+        self.safety.to_tokens(tokens);
         self.static_token.to_tokens(tokens);
         self.mutability.to_tokens(tokens);
         self.ident.to_tokens(tokens);
@@ -454,6 +421,8 @@ impl From<ItemStatic> for FlexibleItemStatic {
         Self {
             attrs: item_static.attrs,
             vis: item_static.vis,
+            unsafety: None,
+            safety: None,
             static_token: item_static.static_token,
             mutability: item_static.mutability,
             ident: item_static.ident,
@@ -470,6 +439,8 @@ impl From<ForeignItemStatic> for FlexibleItemStatic {
         Self {
             attrs: item_static.attrs,
             vis: item_static.vis,
+            unsafety: None,
+            safety: None,
             static_token: item_static.static_token,
             mutability: item_static.mutability,
             ident: item_static.ident,
@@ -481,6 +452,59 @@ impl From<ForeignItemStatic> for FlexibleItemStatic {
     }
 }
 
+/// A foreign function or static, which unlike [`ForeignItem`] can be `safe`.
+#[derive(Clone)]
+pub enum FlexibleForeignItem {
+    Fn(FlexibleItemFn),
+    Static(FlexibleItemStatic),
+}
+
+impl FlexibleForeignItem {
+    /// Converts a foreign function or static into a flexible item.
+    ///
+    /// Returns `None` for other foreign items.
+    pub fn from_foreign_item(item: &ForeignItem) -> Option<Self> {
+        match item {
+            ForeignItem::Fn(item_fn) => Some(Self::Fn(item_fn.clone().into())),
+            ForeignItem::Static(item_static) => Some(Self::Static(item_static.clone().into())),
+            ForeignItem::Verbatim(tokens) => syn::parse2(tokens.clone()).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn attrs_mut(&mut self) -> &mut Vec<Attribute> {
+        match self {
+            Self::Fn(item_fn) => &mut item_fn.attrs,
+            Self::Static(item_static) => &mut item_static.attrs,
+        }
+    }
+
+    /// Converts the flexible item back into a foreign item.
+    pub fn into_foreign_item(self) -> ForeignItem {
+        let tokens = self.into_token_stream();
+        syn::parse2(tokens.clone()).unwrap_or(ForeignItem::Verbatim(tokens))
+    }
+}
+
+impl Parse for FlexibleForeignItem {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if ItemClass::at(input.cursor()) == ItemClass::Fn {
+            input.parse().map(Self::Fn)
+        } else {
+            input.parse().map(Self::Static)
+        }
+    }
+}
+
+impl ToTokens for FlexibleForeignItem {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        match self {
+            Self::Fn(item_fn) => item_fn.to_tokens(tokens),
+            Self::Static(item_static) => item_static.to_tokens(tokens),
+        }
+    }
+}
+
 /// A combination of [`ItemType`], [`ForeignItemType`], [`ImplItemType`], and [`TraitItemType`].
 #[derive(Clone)]
 pub struct FlexibleItemType {
@@ -0,0 +1,1633 @@
+use quote::{ToTokens, TokenStreamExt};
+
+use pm2::Span;
+use syn::{
+    ext::IdentExt,
+    parenthesized,
+    parse::{Parse, ParseStream},
+    spanned::Spanned,
+};
+use syn::{token::Paren, Abi, Expr, Ident, LitStr, Meta, Token, Visibility};
+#[cfg(feature = "full")]
+use syn::{LitInt, RangeLimits, Type};
+
+use crate::abi;
+
+#[cfg(feature = "full")]
+pub use self::items::{
+    FlexibleForeignItem, FlexibleItemConst, FlexibleItemFn, FlexibleItemStatic, FlexibleItemType,
+    QualifiedItem,
+};
+
+/// Items that are parsed with the full syntax tree of `syn`.
+#[cfg(feature = "full")]
+mod items;
+
+/// A qualifier.
+#[derive(Clone)]
+pub enum Qualifier {
+    Visibility(Change<Visibility>),
+    Defaultness(Change<Token![default]>),
+    Constness(Change<Const>),
+    Asyncness(Change<Async>),
+    /// Removes `async` along with the `.await` expressions in the body, i.e. `!async(strip_await)`.
+    StripAwait(kw::strip_await),
+    Unsafety(Change<Token![unsafe]>),
+    Abi(Change<Extern>),
+    Mutability(Change<Token![mut]>),
+    Autoness(Change<Token![auto]>),
+    Safety(Change<kw::safe>),
+}
+
+impl Qualifier {
+    /// Returns the name of what the qualifier changes, as used in error messages.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Visibility(_) => "visibility",
+            Self::Defaultness(_) => "defaultness",
+            Self::Constness(_) => "constness",
+            Self::Asyncness(_) | Self::StripAwait(_) => "asyncness",
+            Self::Unsafety(_) => "unsafety",
+            Self::Abi(_) => "ABI",
+            Self::Mutability(_) => "mutability",
+            Self::Autoness(_) => "autoness",
+            Self::Safety(_) => "safety",
+        }
+    }
+
+    /// Returns the span of the qualifier in the attribute.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Visibility(change) => change.span(),
+            Self::Defaultness(change) => change.span(),
+            Self::Constness(change) => change.span(),
+            Self::Asyncness(change) => change.span(),
+            Self::StripAwait(strip_await_token) => strip_await_token.span,
+            Self::Unsafety(change) => change.span(),
+            Self::Abi(change) => change.span(),
+            Self::Mutability(change) => change.span(),
+            Self::Autoness(change) => change.span(),
+            Self::Safety(change) => change.span(),
+        }
+    }
+}
+
+impl Parse for Qualifier {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![priv]) {
+            let priv_token = input.parse::<Token![priv]>()?;
+            return Ok(Self::Visibility(Change::Clear(priv_token.span)));
+        }
+
+        if input.peek(Token![!]) {
+            let bang_token = input.parse::<Token![!]>()?;
+            let lookahead = input.lookahead1();
+            return if lookahead.peek(Token![default]) {
+                let token = input.parse::<Token![default]>()?;
+                Ok(Self::Defaultness(Change::Clear(token.span)))
+            } else if lookahead.peek(Token![const]) {
+                let token = input.parse::<Token![const]>()?;
+                Ok(Self::Constness(Change::Clear(token.span)))
+            } else if lookahead.peek(Token![async]) {
+                let token = input.parse::<Token![async]>()?;
+                if input.peek(Paren) {
+                    let content;
+                    let _paren_token = parenthesized!(content in input);
+                    let strip_await_token = content.parse::<kw::strip_await>()?;
+                    content.parse::<syn::parse::Nothing>()?;
+                    Ok(Self::StripAwait(strip_await_token))
+                } else {
+                    Ok(Self::Asyncness(Change::Clear(token.span)))
+                }
+            } else if lookahead.peek(Token![unsafe]) {
+                let token = input.parse::<Token![unsafe]>()?;
+                Ok(Self::Unsafety(Change::Clear(token.span)))
+            } else if lookahead.peek(Token![extern]) {
+                let token = input.parse::<Token![extern]>()?;
+                Ok(Self::Abi(Change::Clear(token.span)))
+            } else if lookahead.peek(Token![mut]) {
+                let token = input.parse::<Token![mut]>()?;
+                Ok(Self::Mutability(Change::Clear(token.span)))
+            } else if lookahead.peek(Token![auto]) {
+                let token = parse_auto(input)?;
+                Ok(Self::Autoness(Change::Clear(token.span)))
+            } else if lookahead.peek(kw::safe) {
+                let token = input.parse::<kw::safe>()?;
+                Ok(Self::Safety(Change::Clear(token.span)))
+            } else if lookahead.peek(Token![pub]) {
+                Err(syn::Error::new(
+                    bang_token.span,
+                    "use `priv` to remove the visibility qualifier",
+                ))
+            } else {
+                Err(lookahead.error())
+            };
+        }
+
+        if input.peek(Token![pub]) {
+            input.parse().map(Change::Set).map(Self::Visibility)
+        } else if input.peek(Token![default]) {
+            input.parse().map(Change::Set).map(Self::Defaultness)
+        } else if input.peek(Token![const]) {
+            input.parse().map(Change::Set).map(Self::Constness)
+        } else if input.peek(Token![async]) {
+            input.parse().map(Change::Set).map(Self::Asyncness)
+        } else if input.peek(Token![unsafe]) {
+            input.parse().map(Change::Set).map(Self::Unsafety)
+        } else if input.peek(Token![extern]) {
+            input.parse().map(Change::Set).map(Self::Abi)
+        } else if input.peek(Token![mut]) {
+            input.parse().map(Change::Set).map(Self::Mutability)
+        } else if input.peek(Token![auto]) {
+            parse_auto(input).map(Change::Set).map(Self::Autoness)
+        } else if input.peek(kw::safe) {
+            input.parse().map(Change::Set).map(Self::Safety)
+        } else {
+            Err(syn::Error::new(input.span(), "expected a qualifier"))
+        }
+    }
+}
+
+impl ToTokens for Qualifier {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        /// Prints a qualifier that is set as it is, and one that is removed with `!`.
+        fn change_to_tokens<T: ToTokens>(
+            change: &Change<T>,
+            keyword: &str,
+            tokens: &mut pm2::TokenStream,
+        ) {
+            match change {
+                Change::Set(value) => value.to_tokens(tokens),
+                Change::Clear(span) => {
+                    Token![!](*span).to_tokens(tokens);
+                    tokens.append(Ident::new(keyword, *span));
+                }
+            }
+        }
+
+        match self {
+            Self::Visibility(Change::Set(visibility)) => visibility.to_tokens(tokens),
+            Self::Visibility(Change::Clear(span)) => Token![priv](*span).to_tokens(tokens),
+            Self::Defaultness(change) => change_to_tokens(change, "default", tokens),
+            Self::Constness(change) => change_to_tokens(change, "const", tokens),
+            Self::Asyncness(change) => change_to_tokens(change, "async", tokens),
+            Self::StripAwait(strip_await_token) => {
+                let span = strip_await_token.span;
+                Token![!](span).to_tokens(tokens);
+                Token![async](span).to_tokens(tokens);
+                Paren(span).surround(tokens, |tokens| strip_await_token.to_tokens(tokens));
+            }
+            Self::Unsafety(change) => change_to_tokens(change, "unsafe", tokens),
+            Self::Abi(change) => change_to_tokens(change, "extern", tokens),
+            Self::Mutability(change) => change_to_tokens(change, "mut", tokens),
+            Self::Autoness(change) => change_to_tokens(change, "auto", tokens),
+            Self::Safety(change) => change_to_tokens(change, "safe", tokens),
+        }
+    }
+}
+
+#[cfg(feature = "auto_traits")]
+fn parse_auto(input: ParseStream) -> syn::Result<Token![auto]> {
+    input.parse()
+}
+
+#[cfg(not(feature = "auto_traits"))]
+fn parse_auto(input: ParseStream) -> syn::Result<Token![auto]> {
+    Err(syn::Error::new(
+        input.span(),
+        "the `auto` qualifier requires the `auto_traits` feature of `qualifier_attr`",
+    ))
+}
+
+/// A `const` qualifier, which may skip checking the body of the function, i.e.
+/// `const(unchecked)`.
+#[derive(Clone)]
+pub struct Const {
+    pub const_token: Token![const],
+    pub unchecked: Option<kw::unchecked>,
+}
+
+impl Parse for Const {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let const_token = input.parse()?;
+        let unchecked = if input.peek(Paren) {
+            let content;
+            let _paren_token = parenthesized!(content in input);
+            let unchecked = content.parse()?;
+            content.parse::<syn::parse::Nothing>()?;
+            Some(unchecked)
+        } else {
+            None
+        };
+        Ok(Self {
+            const_token,
+            unchecked,
+        })
+    }
+}
+
+impl ToTokens for Const {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        self.const_token.to_tokens(tokens);
+        if let Some(unchecked) = &self.unchecked {
+            Paren(unchecked.span).surround(tokens, |tokens| unchecked.to_tokens(tokens));
+        }
+    }
+}
+
+/// An `extern "ABI"` qualifier, which may skip checking the function for types that foreign
+/// code can't use or checking the name of the ABI, or write a C prototype of the function to a
+/// header, e.g. `extern "C"(unchecked)`, `extern "x86-interrupt"(unchecked_abi)`, or
+/// `extern "C"(header = "mylib.h")`.
+#[derive(Clone)]
+pub struct Extern {
+    pub abi: Abi,
+    pub unchecked: Option<kw::unchecked>,
+    pub unchecked_abi: Option<kw::unchecked_abi>,
+    pub header: Option<LitStr>,
+}
+
+impl Parse for Extern {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let abi = input.parse()?;
+        let mut unchecked = None;
+        let mut unchecked_abi = None;
+        let mut header = None;
+        if input.peek(Paren) {
+            let content;
+            let _paren_token = parenthesized!(content in input);
+            loop {
+                let lookahead = content.lookahead1();
+                if lookahead.peek(kw::unchecked) {
+                    let unchecked_token = content.parse::<kw::unchecked>()?;
+                    if unchecked.replace(unchecked_token).is_some() {
+                        return Err(syn::Error::new(
+                            unchecked_token.span,
+                            "`unchecked` already specified",
+                        ));
+                    }
+                } else if lookahead.peek(kw::unchecked_abi) {
+                    let unchecked_abi_token = content.parse::<kw::unchecked_abi>()?;
+                    if unchecked_abi.replace(unchecked_abi_token).is_some() {
+                        return Err(syn::Error::new(
+                            unchecked_abi_token.span,
+                            "`unchecked_abi` already specified",
+                        ));
+                    }
+                } else if lookahead.peek(kw::header) {
+                    let header_token = content.parse::<kw::header>()?;
+                    content.parse::<Token![=]>()?;
+                    if header.replace(content.parse::<LitStr>()?).is_some() {
+                        return Err(syn::Error::new(
+                            header_token.span,
+                            "`header` already specified",
+                        ));
+                    }
+                } else {
+                    return Err(lookahead.error());
+                }
+                if content.is_empty() {
+                    break;
+                }
+                content.parse::<Token![,]>()?;
+                if content.is_empty() {
+                    break;
+                }
+            }
+        }
+        if unchecked_abi.is_none() {
+            abi::check_abi(&abi)?;
+        }
+        Ok(Self {
+            abi,
+            unchecked,
+            unchecked_abi,
+            header,
+        })
+    }
+}
+
+impl ToTokens for Extern {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        self.abi.to_tokens(tokens);
+        if self.unchecked.is_none() && self.unchecked_abi.is_none() && self.header.is_none() {
+            return;
+        }
+        Paren(self.abi.extern_token.span).surround(tokens, |tokens| {
+            if let Some(unchecked) = &self.unchecked {
+                unchecked.to_tokens(tokens);
+                <Token![,]>::default().to_tokens(tokens);
+            }
+            if let Some(unchecked_abi) = &self.unchecked_abi {
+                unchecked_abi.to_tokens(tokens);
+                <Token![,]>::default().to_tokens(tokens);
+            }
+            if let Some(header) = &self.header {
+                kw::header(header.span()).to_tokens(tokens);
+                <Token![=]>::default().to_tokens(tokens);
+                header.to_tokens(tokens);
+            }
+        });
+    }
+}
+
+/// An `async` qualifier, which may desugar the function, e.g. `async(send)`.
+#[derive(Clone)]
+pub struct Async {
+    pub async_token: Token![async],
+    pub desugar: Option<AsyncDesugar>,
+}
+
+/// The desugaring of an `async` function into one that returns `impl Future`.
+#[derive(Clone)]
+pub struct AsyncDesugar {
+    pub paren_token: Paren,
+    /// Whether the future must be `Send`, i.e. `async(send)` rather than `async(desugar)`.
+    pub send: Option<kw::send>,
+}
+
+impl Parse for Async {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let async_token = input.parse()?;
+        let desugar = if input.peek(Paren) {
+            let content;
+            let paren_token = parenthesized!(content in input);
+            let lookahead = content.lookahead1();
+            let send = if lookahead.peek(kw::send) {
+                Some(content.parse()?)
+            } else if lookahead.peek(kw::desugar) {
+                content.parse::<kw::desugar>()?;
+                None
+            } else {
+                return Err(lookahead.error());
+            };
+            content.parse::<syn::parse::Nothing>()?;
+            Some(AsyncDesugar { paren_token, send })
+        } else {
+            None
+        };
+        Ok(Self {
+            async_token,
+            desugar,
+        })
+    }
+}
+
+impl ToTokens for Async {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        self.async_token.to_tokens(tokens);
+        if let Some(desugar) = &self.desugar {
+            desugar
+                .paren_token
+                .surround(tokens, |tokens| match &desugar.send {
+                    Some(send) => send.to_tokens(tokens),
+                    None => tokens.append(Ident::new("desugar", desugar.paren_token.span.join())),
+                });
+        }
+    }
+}
+
+/// A change to a single qualifier of an item.
+#[derive(Clone)]
+pub enum Change<T> {
+    /// Sets the qualifier, e.g. `const`.
+    Set(T),
+    /// Removes the qualifier, e.g. `!const` or `priv`.
+    Clear(Span),
+}
+
+impl<T> Change<T> {
+    /// Maps the value of a set qualifier.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Change<U> {
+        match self {
+            Self::Set(value) => Change::Set(f(value)),
+            Self::Clear(span) => Change::Clear(span),
+        }
+    }
+}
+
+impl<T: Spanned> Change<T> {
+    /// Returns the span of the qualifier in the attribute.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Set(value) => value.span(),
+            Self::Clear(span) => *span,
+        }
+    }
+}
+
+pub mod kw {
+    syn::custom_keyword!(abort);
+    syn::custom_keyword!(desugar);
+    syn::custom_keyword!(dual);
+    syn::custom_keyword!(each);
+    syn::custom_keyword!(exact);
+    syn::custom_keyword!(export);
+    syn::custom_keyword!(extern_shim);
+    syn::custom_keyword!(header);
+    syn::custom_keyword!(except);
+    syn::custom_keyword!(lenient);
+    syn::custom_keyword!(name);
+    syn::custom_keyword!(nightly);
+    syn::custom_keyword!(on_panic);
+    syn::custom_keyword!(panic);
+    syn::custom_keyword!(prefix);
+    syn::custom_keyword!(safe);
+    syn::custom_keyword!(send);
+    syn::custom_keyword!(since);
+    syn::custom_keyword!(strip_await);
+    syn::custom_keyword!(suffix);
+    syn::custom_keyword!(unchecked);
+    syn::custom_keyword!(unchecked_abi);
+}
+
+/// A kind of item that `each` can be restricted to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MemberKind {
+    Const,
+    Enum,
+    Fn,
+    Impl,
+    Mod,
+    Static,
+    Struct,
+    Trait,
+    Type,
+    Union,
+    Use,
+}
+
+impl Parse for MemberKind {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lookahead = input.lookahead1();
+        if lookahead.peek(Token![const]) {
+            input.parse::<Token![const]>()?;
+            Ok(Self::Const)
+        } else if lookahead.peek(Token![enum]) {
+            input.parse::<Token![enum]>()?;
+            Ok(Self::Enum)
+        } else if lookahead.peek(Token![fn]) {
+            input.parse::<Token![fn]>()?;
+            Ok(Self::Fn)
+        } else if lookahead.peek(Token![impl]) {
+            input.parse::<Token![impl]>()?;
+            Ok(Self::Impl)
+        } else if lookahead.peek(Token![mod]) {
+            input.parse::<Token![mod]>()?;
+            Ok(Self::Mod)
+        } else if lookahead.peek(Token![static]) {
+            input.parse::<Token![static]>()?;
+            Ok(Self::Static)
+        } else if lookahead.peek(Token![struct]) {
+            input.parse::<Token![struct]>()?;
+            Ok(Self::Struct)
+        } else if lookahead.peek(Token![trait]) {
+            input.parse::<Token![trait]>()?;
+            Ok(Self::Trait)
+        } else if lookahead.peek(Token![type]) {
+            input.parse::<Token![type]>()?;
+            Ok(Self::Type)
+        } else if lookahead.peek(Token![union]) {
+            input.parse::<Token![union]>()?;
+            Ok(Self::Union)
+        } else if lookahead.peek(Token![use]) {
+            input.parse::<Token![use]>()?;
+            Ok(Self::Use)
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+impl MemberKind {
+    /// Returns the keyword of the kind.
+    pub fn keyword(self) -> &'static str {
+        match self {
+            Self::Const => "const",
+            Self::Enum => "enum",
+            Self::Fn => "fn",
+            Self::Impl => "impl",
+            Self::Mod => "mod",
+            Self::Static => "static",
+            Self::Struct => "struct",
+            Self::Trait => "trait",
+            Self::Type => "type",
+            Self::Union => "union",
+            Self::Use => "use",
+        }
+    }
+}
+
+/// Applies the qualifiers to the members of a container instead of the container itself,
+/// e.g. `each`, `each(fn)`, or `each(const, type, "get_*", lenient)`.
+#[derive(Clone)]
+pub struct Each {
+    pub each_token: kw::each,
+    /// The kinds of members to qualify. Every kind is qualified if this is empty.
+    pub kinds: Vec<MemberKind>,
+    /// Glob patterns for the names of members to qualify. Every name is qualified if this is empty.
+    pub patterns: Vec<LitStr>,
+    /// When present, members that don't support every qualifier are skipped instead of reported.
+    pub lenient: Option<kw::lenient>,
+}
+
+impl Parse for Each {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let each_token = input.parse()?;
+        let mut kinds = Vec::new();
+        let mut patterns = Vec::new();
+        let mut lenient = None;
+
+        if input.peek(Paren) {
+            let content;
+            let _paren_token = parenthesized!(content in input);
+            while !content.is_empty() {
+                if content.peek(LitStr) {
+                    patterns.push(content.parse()?);
+                } else if content.peek(kw::lenient) {
+                    let lenient_token = content.parse::<kw::lenient>()?;
+                    if lenient.is_some() {
+                        return Err(syn::Error::new(
+                            lenient_token.span,
+                            "lenient mode already specified",
+                        ));
+                    }
+                    lenient = Some(lenient_token);
+                } else {
+                    let span = content.span();
+                    let kind = content.parse::<MemberKind>()?;
+                    if kinds.contains(&kind) {
+                        return Err(syn::Error::new(span, "item kind already specified"));
+                    }
+                    kinds.push(kind);
+                }
+                if !content.is_empty() {
+                    content.parse::<Token![,]>()?;
+                }
+            }
+        }
+
+        Ok(Self {
+            each_token,
+            kinds,
+            patterns,
+            lenient,
+        })
+    }
+}
+
+impl ToTokens for Each {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        let span = self.each_token.span;
+        self.each_token.to_tokens(tokens);
+        if self.kinds.is_empty() && self.patterns.is_empty() && self.lenient.is_none() {
+            return;
+        }
+        Paren(span).surround(tokens, |tokens| {
+            for kind in &self.kinds {
+                tokens.append(Ident::new(kind.keyword(), span));
+                <Token![,]>::default().to_tokens(tokens);
+            }
+            for pattern in &self.patterns {
+                pattern.to_tokens(tokens);
+                <Token![,]>::default().to_tokens(tokens);
+            }
+            self.lenient.to_tokens(tokens);
+        });
+    }
+}
+
+/// Emits a renamed twin of a function with a qualifier toggled, e.g.
+/// `dual(async, suffix = "_async")` or `dual(const, prefix = "runtime_")`.
+#[derive(Clone)]
+pub struct Dual {
+    pub dual_token: kw::dual,
+    pub toggle: DualToggle,
+    pub affix: Affix,
+}
+
+/// The qualifier that differs between a function and its twin.
+#[derive(Clone, Copy)]
+pub enum DualToggle {
+    Async(Token![async]),
+    Const(Token![const]),
+    Unsafe(Token![unsafe]),
+}
+
+/// How the name of a twin is derived from the original name.
+#[derive(Clone)]
+pub enum Affix {
+    Prefix(LitStr),
+    Suffix(LitStr),
+}
+
+impl Parse for Dual {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let dual_token = input.parse::<kw::dual>()?;
+        let content;
+        let _paren_token = parenthesized!(content in input);
+
+        let lookahead = content.lookahead1();
+        let toggle = if lookahead.peek(Token![async]) {
+            DualToggle::Async(content.parse()?)
+        } else if lookahead.peek(Token![const]) {
+            DualToggle::Const(content.parse()?)
+        } else if lookahead.peek(Token![unsafe]) {
+            DualToggle::Unsafe(content.parse()?)
+        } else {
+            return Err(lookahead.error());
+        };
+        if content.is_empty() {
+            return Err(syn::Error::new(
+                dual_token.span,
+                "expected `prefix = \"...\"` or `suffix = \"...\"` to name the twin",
+            ));
+        }
+        content.parse::<Token![,]>()?;
+
+        let lookahead = content.lookahead1();
+        let affix = if lookahead.peek(kw::prefix) {
+            content.parse::<kw::prefix>()?;
+            content.parse::<Token![=]>()?;
+            Affix::Prefix(content.parse()?)
+        } else if lookahead.peek(kw::suffix) {
+            content.parse::<kw::suffix>()?;
+            content.parse::<Token![=]>()?;
+            Affix::Suffix(content.parse()?)
+        } else {
+            return Err(lookahead.error());
+        };
+        if !content.is_empty() {
+            content.parse::<Token![,]>()?;
+        }
+        content.parse::<syn::parse::Nothing>()?;
+
+        Ok(Self {
+            dual_token,
+            toggle,
+            affix,
+        })
+    }
+}
+
+impl ToTokens for Dual {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        let span = self.dual_token.span;
+        self.dual_token.to_tokens(tokens);
+        Paren(span).surround(tokens, |tokens| {
+            match &self.toggle {
+                DualToggle::Async(async_token) => async_token.to_tokens(tokens),
+                DualToggle::Const(const_token) => const_token.to_tokens(tokens),
+                DualToggle::Unsafe(unsafe_token) => unsafe_token.to_tokens(tokens),
+            }
+            <Token![,]>::default().to_tokens(tokens);
+            let affix = match &self.affix {
+                Affix::Prefix(affix) => {
+                    kw::prefix(span).to_tokens(tokens);
+                    affix
+                }
+                Affix::Suffix(affix) => {
+                    kw::suffix(span).to_tokens(tokens);
+                    affix
+                }
+            };
+            <Token![=]>::default().to_tokens(tokens);
+            affix.to_tokens(tokens);
+        });
+    }
+}
+
+/// Exports a function under its own name or another one, e.g. `export`,
+/// `export(name = "foo_c")`, or `export(unsafe, prefix = "mylib_")`.
+#[derive(Clone)]
+pub struct Export {
+    pub export_token: kw::export,
+    /// Wraps the emitted attribute in `unsafe(...)`, as edition 2024 writes it.
+    pub unsafe_token: Option<Token![unsafe]>,
+    pub name: Option<LitStr>,
+    pub prefix: Option<LitStr>,
+}
+
+impl Export {
+    /// Returns the name of the symbol that a function with the given name is exported under.
+    pub fn symbol(&self, ident: &Ident) -> String {
+        match (&self.name, &self.prefix) {
+            (Some(name), _) => name.value(),
+            (None, Some(prefix)) => format!("{}{}", prefix.value(), ident.unraw()),
+            (None, None) => ident.unraw().to_string(),
+        }
+    }
+}
+
+impl Parse for Export {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let export_token = input.parse::<kw::export>()?;
+        let mut unsafe_token = None;
+        let mut name = None;
+        let mut prefix = None;
+        if input.peek(Paren) {
+            let content;
+            let _paren_token = parenthesized!(content in input);
+            while !content.is_empty() {
+                let lookahead = content.lookahead1();
+                if lookahead.peek(Token![unsafe]) {
+                    let token = content.parse::<Token![unsafe]>()?;
+                    if unsafe_token.replace(token).is_some() {
+                        return Err(syn::Error::new(token.span, "`unsafe` already specified"));
+                    }
+                } else if lookahead.peek(kw::name) || lookahead.peek(kw::prefix) {
+                    let span = content.span();
+                    if name.is_some() || prefix.is_some() {
+                        return Err(syn::Error::new(
+                            span,
+                            "expected either `name = \"...\"` or `prefix = \"...\"`",
+                        ));
+                    }
+                    if content.peek(kw::name) {
+                        content.parse::<kw::name>()?;
+                        content.parse::<Token![=]>()?;
+                        name = Some(content.parse()?);
+                    } else {
+                        content.parse::<kw::prefix>()?;
+                        content.parse::<Token![=]>()?;
+                        prefix = Some(content.parse()?);
+                    }
+                } else {
+                    return Err(lookahead.error());
+                }
+                if !content.is_empty() {
+                    content.parse::<Token![,]>()?;
+                }
+            }
+        }
+        Ok(Self {
+            export_token,
+            unsafe_token,
+            name,
+            prefix,
+        })
+    }
+}
+
+impl ToTokens for Export {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        let span = self.export_token.span;
+        self.export_token.to_tokens(tokens);
+        let named = match (&self.name, &self.prefix) {
+            (Some(name), _) => Some(("name", name)),
+            (None, Some(prefix)) => Some(("prefix", prefix)),
+            (None, None) => None,
+        };
+        if self.unsafe_token.is_none() && named.is_none() {
+            return;
+        }
+        Paren(span).surround(tokens, |tokens| {
+            if let Some(unsafe_token) = &self.unsafe_token {
+                unsafe_token.to_tokens(tokens);
+                if named.is_some() {
+                    <Token![,]>::default().to_tokens(tokens);
+                }
+            }
+            if let Some((name, value)) = named {
+                tokens.append(Ident::new(name, span));
+                <Token![=]>::default().to_tokens(tokens);
+                value.to_tokens(tokens);
+            }
+        });
+    }
+}
+
+/// Emits an exported wrapper of a function with the given ABI instead of changing the ABI of
+/// the function itself, e.g. `extern_shim("C", name = "foo_c", on_panic = -1)`.
+#[derive(Clone)]
+pub struct ExternShim {
+    pub extern_shim_token: kw::extern_shim,
+    pub abi: Abi,
+    /// The name of the exported symbol, which is the name of the function by default.
+    pub name: Option<LitStr>,
+    pub on_panic: OnPanic,
+    /// `panic = "abort"`, for crates built with `panic = "abort"` or without `std`: the wrapper
+    /// calls the function without catching panics.
+    pub panic_abort: Option<kw::panic>,
+    /// Wraps the attribute that exports the wrapper in `unsafe(...)`, as edition 2024 writes it.
+    pub unsafe_token: Option<Token![unsafe]>,
+    pub unchecked: Option<kw::unchecked>,
+    pub unchecked_abi: Option<kw::unchecked_abi>,
+}
+
+/// What a wrapper emitted by `extern_shim` does when the function panics.
+#[derive(Clone)]
+pub enum OnPanic {
+    Abort,
+    /// Returns the value of the expression, e.g. an error code.
+    Return(Expr),
+}
+
+impl Parse for ExternShim {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let extern_shim_token = input.parse::<kw::extern_shim>()?;
+        let content;
+        let _paren_token = parenthesized!(content in input);
+
+        let abi = Abi {
+            extern_token: Token![extern](extern_shim_token.span),
+            name: Some(content.parse()?),
+        };
+        let mut name = None;
+        let mut on_panic = None;
+        let mut panic_abort = None;
+        let mut unsafe_token = None;
+        let mut unchecked = None;
+        let mut unchecked_abi = None;
+        while !content.is_empty() {
+            content.parse::<Token![,]>()?;
+            if content.is_empty() {
+                break;
+            }
+            let lookahead = content.lookahead1();
+            if lookahead.peek(kw::name) {
+                let name_token = content.parse::<kw::name>()?;
+                content.parse::<Token![=]>()?;
+                if name.replace(content.parse::<LitStr>()?).is_some() {
+                    return Err(syn::Error::new(name_token.span, "`name` already specified"));
+                }
+            } else if lookahead.peek(kw::on_panic) {
+                let on_panic_token = content.parse::<kw::on_panic>()?;
+                content.parse::<Token![=]>()?;
+                let action = if content.peek(kw::abort) {
+                    content.parse::<kw::abort>()?;
+                    OnPanic::Abort
+                } else {
+                    OnPanic::Return(content.parse()?)
+                };
+                if on_panic.replace(action).is_some() {
+                    return Err(syn::Error::new(
+                        on_panic_token.span,
+                        "`on_panic` already specified",
+                    ));
+                }
+            } else if lookahead.peek(kw::panic) {
+                let panic_token = content.parse::<kw::panic>()?;
+                content.parse::<Token![=]>()?;
+                let strategy = content.parse::<LitStr>()?;
+                if strategy.value() != "abort" {
+                    return Err(syn::Error::new(
+                        strategy.span(),
+                        "expected `panic = \"abort\"`, the only strategy that needs no unwinding",
+                    ));
+                }
+                if panic_abort.replace(panic_token).is_some() {
+                    return Err(syn::Error::new(
+                        panic_token.span,
+                        "`panic` already specified",
+                    ));
+                }
+            } else if lookahead.peek(Token![unsafe]) {
+                let token = content.parse::<Token![unsafe]>()?;
+                if unsafe_token.replace(token).is_some() {
+                    return Err(syn::Error::new(token.span, "`unsafe` already specified"));
+                }
+            } else if lookahead.peek(kw::unchecked) {
+                let unchecked_token = content.parse::<kw::unchecked>()?;
+                if unchecked.replace(unchecked_token).is_some() {
+                    return Err(syn::Error::new(
+                        unchecked_token.span,
+                        "`unchecked` already specified",
+                    ));
+                }
+            } else if lookahead.peek(kw::unchecked_abi) {
+                let unchecked_abi_token = content.parse::<kw::unchecked_abi>()?;
+                if unchecked_abi.replace(unchecked_abi_token).is_some() {
+                    return Err(syn::Error::new(
+                        unchecked_abi_token.span,
+                        "`unchecked_abi` already specified",
+                    ));
+                }
+            } else {
+                return Err(lookahead.error());
+            }
+        }
+
+        if let (Some(panic_token), Some(_)) = (panic_abort, &on_panic) {
+            return Err(syn::Error::new(
+                panic_token.span,
+                "`panic = \"abort\"` can't be combined with `on_panic`, since panics aren't caught",
+            ));
+        }
+        if unchecked_abi.is_none() {
+            abi::check_abi(&abi)?;
+        }
+        Ok(Self {
+            extern_shim_token,
+            abi,
+            name,
+            on_panic: on_panic.unwrap_or(OnPanic::Abort),
+            panic_abort,
+            unsafe_token,
+            unchecked,
+            unchecked_abi,
+        })
+    }
+}
+
+impl ToTokens for ExternShim {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        let span = self.extern_shim_token.span;
+        self.extern_shim_token.to_tokens(tokens);
+        Paren(span).surround(tokens, |tokens| {
+            self.abi.name.to_tokens(tokens);
+            if let Some(name) = &self.name {
+                <Token![,]>::default().to_tokens(tokens);
+                kw::name(span).to_tokens(tokens);
+                <Token![=]>::default().to_tokens(tokens);
+                name.to_tokens(tokens);
+            }
+            if let OnPanic::Return(expr) = &self.on_panic {
+                <Token![,]>::default().to_tokens(tokens);
+                kw::on_panic(span).to_tokens(tokens);
+                <Token![=]>::default().to_tokens(tokens);
+                expr.to_tokens(tokens);
+            }
+            if let Some(panic_token) = &self.panic_abort {
+                <Token![,]>::default().to_tokens(tokens);
+                panic_token.to_tokens(tokens);
+                <Token![=]>::default().to_tokens(tokens);
+                LitStr::new("abort", span).to_tokens(tokens);
+            }
+            if let Some(unsafe_token) = &self.unsafe_token {
+                <Token![,]>::default().to_tokens(tokens);
+                unsafe_token.to_tokens(tokens);
+            }
+            if let Some(unchecked) = &self.unchecked {
+                <Token![,]>::default().to_tokens(tokens);
+                unchecked.to_tokens(tokens);
+            }
+            if let Some(unchecked_abi) = &self.unchecked_abi {
+                <Token![,]>::default().to_tokens(tokens);
+                unchecked_abi.to_tokens(tokens);
+            }
+        });
+    }
+}
+
+/// A set of qualifiers.
+#[derive(Clone, Default)]
+pub struct Qualifiers {
+    /// When present, every qualifier that is not listed is removed from the item.
+    pub exact: Option<kw::exact>,
+    /// When present, the qualifiers are applied to the members of the item instead.
+    pub each: Option<Each>,
+    pub dual: Option<Dual>,
+    pub extern_shim: Option<ExternShim>,
+    pub export: Option<Export>,
+    pub visibility: Option<Change<Visibility>>,
+    pub defaultness: Option<Change<Token![default]>>,
+    pub constness: Option<Change<Const>>,
+    pub asyncness: Option<Change<Async>>,
+    pub unsafety: Option<Change<Token![unsafe]>>,
+    pub abi: Option<Change<Extern>>,
+    pub mutability: Option<Change<Token![mut]>>,
+    pub autoness: Option<Change<Token![auto]>>,
+    pub safety: Option<Change<kw::safe>>,
+    /// Whether removing `async` also strips `.await` from the body.
+    pub strip_await: Option<kw::strip_await>,
+    /// Qualifiers that depend on `cfg` predicates.
+    pub conditions: Vec<Condition>,
+}
+
+impl Parse for Qualifiers {
+    // implement for parsing a list of qualifiers NOT enclosed in square brackets
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut qualifiers = Self::default();
+        // `exact` changes how the whole list applies, so it has to come first.
+        let mut first = true;
+
+        while !input.is_empty() {
+            if input.peek(kw::exact) {
+                let exact_token = input.parse::<kw::exact>()?;
+                if qualifiers.exact.is_some() {
+                    return Err(syn::Error::new(
+                        exact_token.span,
+                        "exact mode already specified",
+                    ));
+                }
+                if !first {
+                    return Err(syn::Error::new(
+                        exact_token.span,
+                        "`exact` has to come first in the list of qualifiers",
+                    ));
+                }
+                qualifiers.exact = Some(exact_token);
+            } else if input.peek(kw::each) {
+                let each_mode = input.parse::<Each>()?;
+                if qualifiers.each.is_some() {
+                    return Err(syn::Error::new(
+                        each_mode.each_token.span,
+                        "each mode already specified",
+                    ));
+                }
+                qualifiers.each = Some(each_mode);
+            } else if input.peek(kw::dual) {
+                let dual_mode = input.parse::<Dual>()?;
+                if qualifiers.dual.is_some() {
+                    return Err(syn::Error::new(
+                        dual_mode.dual_token.span,
+                        "dual mode already specified",
+                    ));
+                }
+                qualifiers.dual = Some(dual_mode);
+            } else if input.peek(kw::extern_shim) {
+                let extern_shim = input.parse::<ExternShim>()?;
+                if qualifiers.extern_shim.is_some() {
+                    return Err(syn::Error::new(
+                        extern_shim.extern_shim_token.span,
+                        "extern shim already specified",
+                    ));
+                }
+                qualifiers.extern_shim = Some(extern_shim);
+            } else if input.peek(kw::export) {
+                let export = input.parse::<Export>()?;
+                if qualifiers.export.is_some() {
+                    return Err(syn::Error::new(
+                        export.export_token.span,
+                        "export already specified",
+                    ));
+                }
+                qualifiers.export = Some(export);
+            } else if peek_const_gate(input) {
+                let condition = input.call(parse_const_gate)?;
+                qualifiers.insert_condition(condition)?;
+            } else {
+                let qualifier = input.parse::<Qualifier>()?;
+                if input.peek(Token![if]) {
+                    input.parse::<Token![if]>()?;
+                    let condition = Condition {
+                        qualifier,
+                        predicate: input.parse()?,
+                        otherwise: if input.peek(Token![else]) {
+                            Some((input.parse()?, input.parse()?))
+                        } else {
+                            None
+                        },
+                    };
+                    qualifiers.insert_condition(condition)?;
+                } else {
+                    qualifiers.check_unconditional(&qualifier)?;
+                    qualifiers.insert(qualifier)?;
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+            first = false;
+        }
+
+        if let (Some(Change::Set(safe_token)), Some(Change::Set(_))) =
+            (&qualifiers.safety, &qualifiers.unsafety)
+        {
+            return Err(syn::Error::new(
+                safe_token.span,
+                "`safe` and `unsafe` cannot be combined",
+            ));
+        }
+
+        Ok(qualifiers)
+    }
+}
+
+/// Prints the qualifiers as a list that parses back into the same qualifiers, e.g. for the
+/// arguments of an attribute.
+impl ToTokens for Qualifiers {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        let mut list = Vec::new();
+        if let Some(exact) = &self.exact {
+            list.push(exact.to_token_stream());
+        }
+        if let Some(each) = &self.each {
+            list.push(each.to_token_stream());
+        }
+        if let Some(dual) = &self.dual {
+            list.push(dual.to_token_stream());
+        }
+        if let Some(extern_shim) = &self.extern_shim {
+            list.push(extern_shim.to_token_stream());
+        }
+        if let Some(export) = &self.export {
+            list.push(export.to_token_stream());
+        }
+        let asyncness = match (&self.strip_await, &self.asyncness) {
+            (Some(strip_await_token), Some(Change::Clear(_))) => {
+                Some(Qualifier::StripAwait(*strip_await_token))
+            }
+            (_, asyncness) => asyncness.clone().map(Qualifier::Asyncness),
+        };
+        let qualifiers = [
+            self.visibility.clone().map(Qualifier::Visibility),
+            self.defaultness.clone().map(Qualifier::Defaultness),
+            self.constness.clone().map(Qualifier::Constness),
+            asyncness,
+            self.unsafety.clone().map(Qualifier::Unsafety),
+            self.abi.clone().map(Qualifier::Abi),
+            self.mutability.clone().map(Qualifier::Mutability),
+            self.autoness.clone().map(Qualifier::Autoness),
+            self.safety.clone().map(Qualifier::Safety),
+        ];
+        for qualifier in qualifiers.iter().flatten() {
+            list.push(qualifier.to_token_stream());
+        }
+        for condition in &self.conditions {
+            list.push(condition.to_token_stream());
+        }
+        tokens.append_separated(list, <Token![,]>::default());
+    }
+}
+
+impl Qualifiers {
+    /// Adds a qualifier to the set.
+    fn insert(&mut self, qualifier: Qualifier) -> syn::Result<()> {
+        fn insert_change<T: Spanned>(
+            target: &mut Option<Change<T>>,
+            change: Change<T>,
+            name: &str,
+        ) -> syn::Result<()> {
+            if target.is_some() {
+                return Err(syn::Error::new(
+                    change.span(),
+                    format!("{} already specified", name),
+                ));
+            }
+            *target = Some(change);
+            Ok(())
+        }
+
+        let name = qualifier.name();
+        match qualifier {
+            Qualifier::Visibility(change) => insert_change(&mut self.visibility, change, name),
+            Qualifier::Defaultness(change) => insert_change(&mut self.defaultness, change, name),
+            Qualifier::Constness(change) => insert_change(&mut self.constness, change, name),
+            Qualifier::Asyncness(change) => insert_change(&mut self.asyncness, change, name),
+            Qualifier::StripAwait(strip_await_token) => {
+                insert_change(
+                    &mut self.asyncness,
+                    Change::Clear(strip_await_token.span),
+                    name,
+                )?;
+                self.strip_await = Some(strip_await_token);
+                Ok(())
+            }
+            Qualifier::Unsafety(change) => insert_change(&mut self.unsafety, change, name),
+            Qualifier::Abi(change) => insert_change(&mut self.abi, change, name),
+            Qualifier::Mutability(change) => insert_change(&mut self.mutability, change, name),
+            Qualifier::Autoness(change) => insert_change(&mut self.autoness, change, name),
+            Qualifier::Safety(change) => insert_change(&mut self.safety, change, name),
+        }
+    }
+
+    /// Reports an unconditional qualifier that changes the same thing as a conditional one.
+    fn check_unconditional(&self, qualifier: &Qualifier) -> syn::Result<()> {
+        if self
+            .conditions
+            .iter()
+            .any(|condition| condition.qualifier.name() == qualifier.name())
+        {
+            return Err(syn::Error::new(
+                qualifier.span(),
+                format!("{} already specified", qualifier.name()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Adds a conditional qualifier to the set.
+    ///
+    /// Each qualifier can only be changed once, so conditions can't overlap. Alternatives must
+    /// be given with `else` instead.
+    fn insert_condition(&mut self, condition: Condition) -> syn::Result<()> {
+        let name = condition.qualifier.name();
+        if let Some((_, otherwise)) = &condition.otherwise {
+            if otherwise.name() != name {
+                return Err(syn::Error::new(
+                    otherwise.span(),
+                    format!("expected the `else` branch to change the {} as well", name),
+                ));
+            }
+        }
+        // Check against the unconditional qualifiers without changing them.
+        self.clone().insert(condition.qualifier.clone())?;
+        self.check_unconditional(&condition.qualifier)?;
+        self.conditions.push(condition);
+        Ok(())
+    }
+
+    /// Reports the modes that only `#[qualifiers]` itself supports, for attributes that qualify
+    /// fields or members directly.
+    pub fn check_item_only(&self) -> syn::Result<()> {
+        if let Some(each) = &self.each {
+            return Err(syn::Error::new(
+                each.each_token.span,
+                "`each` is not supported here",
+            ));
+        }
+        if let Some(dual) = &self.dual {
+            return Err(syn::Error::new(
+                dual.dual_token.span,
+                "`dual` is not supported here",
+            ));
+        }
+        if let Some(extern_shim) = &self.extern_shim {
+            return Err(syn::Error::new(
+                extern_shim.extern_shim_token.span,
+                "`extern_shim` is not supported here",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the distinct `cfg` predicates of the conditional qualifiers.
+    pub fn predicates(&self) -> Vec<Meta> {
+        let mut predicates = Vec::new();
+        for condition in &self.conditions {
+            push_predicate(&mut predicates, &condition.predicate);
+        }
+        predicates
+    }
+
+    /// Returns the qualifiers that apply when exactly the predicates for which `holds` returns
+    /// `true` hold.
+    ///
+    /// Reports conditional qualifiers that change the same thing as others when their predicates
+    /// hold together.
+    pub fn resolve(&self, holds: &dyn Fn(&Meta) -> bool) -> syn::Result<Self> {
+        let mut qualifiers = self.clone();
+        let mut errors = Vec::new();
+        for condition in std::mem::take(&mut qualifiers.conditions) {
+            let qualifier = if holds(&condition.predicate) {
+                Some(condition.qualifier)
+            } else {
+                condition.otherwise.map(|(_, otherwise)| otherwise)
+            };
+            if let Some(qualifier) = qualifier {
+                if let Err(error) = qualifiers.insert(qualifier) {
+                    errors.push(error);
+                }
+            }
+        }
+
+        if let Some(error) = errors.into_iter().reduce(|mut error, next| {
+            error.combine(next);
+            error
+        }) {
+            Err(error)
+        } else {
+            Ok(qualifiers)
+        }
+    }
+}
+
+/// Returns whether the input starts with a `const` qualifier gated on the version of the
+/// compiler.
+fn peek_const_gate(input: ParseStream) -> bool {
+    fn peek(input: ParseStream) -> syn::Result<bool> {
+        input.parse::<Token![const]>()?;
+        let content;
+        let _paren_token = parenthesized!(content in input);
+        Ok(content.peek(kw::since) || content.peek(kw::nightly))
+    }
+
+    peek(&input.fork()).unwrap_or(false)
+}
+
+/// Parses a `const` qualifier gated on the version of the compiler, i.e. `const(since = "1.61")`
+/// or `const(nightly)`, into a condition on the cfgs emitted by `qualifier_attr_build`.
+///
+/// Like other `const` qualifiers, these may be followed by `unchecked`, e.g.
+/// `const(nightly, unchecked)`.
+fn parse_const_gate(input: ParseStream) -> syn::Result<Condition> {
+    let const_token = input.parse::<Token![const]>()?;
+    let content;
+    let _paren_token = parenthesized!(content in input);
+
+    let lookahead = content.lookahead1();
+    let cfg = if lookahead.peek(kw::nightly) {
+        let nightly_token = content.parse::<kw::nightly>()?;
+        Ident::new("qualifier_attr_nightly", nightly_token.span)
+    } else if lookahead.peek(kw::since) {
+        content.parse::<kw::since>()?;
+        content.parse::<Token![=]>()?;
+        let version = content.parse::<LitStr>()?;
+        let minor = parse_minor_version(&version)?;
+        Ident::new(&format!("qualifier_attr_since_1_{}", minor), version.span())
+    } else {
+        return Err(lookahead.error());
+    };
+    let unchecked = if content.is_empty() {
+        None
+    } else {
+        content.parse::<Token![,]>()?;
+        Some(content.parse()?)
+    };
+    content.parse::<syn::parse::Nothing>()?;
+
+    Ok(Condition {
+        qualifier: Qualifier::Constness(Change::Set(Const {
+            const_token,
+            unchecked,
+        })),
+        predicate: Meta::Path(cfg.into()),
+        otherwise: None,
+    })
+}
+
+/// Returns whether a predicate is one of the cfgs that `const(since = "...")` and
+/// `const(nightly)` check.
+pub(crate) fn is_version_gate(predicate: &Meta) -> bool {
+    match predicate {
+        Meta::Path(path) => path.get_ident().map_or(false, |ident| {
+            let name = ident.to_string();
+            name == "qualifier_attr_nightly" || name.starts_with("qualifier_attr_since_1_")
+        }),
+        _ => false,
+    }
+}
+
+/// Parses a Rust version like `"1.61"` or `"1.61.0"` and returns its minor version.
+fn parse_minor_version(version: &LitStr) -> syn::Result<u32> {
+    let value = version.value();
+    let mut parts = value.split('.');
+    let minor = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some("1"), Some(minor), None | Some("0"), None) => minor.parse().ok(),
+        _ => None,
+    };
+    minor.ok_or_else(|| {
+        syn::Error::new(
+            version.span(),
+            "expected a Rust version like \"1.61\"; patch versions aren't supported",
+        )
+    })
+}
+
+/// Adds a predicate to a list unless an identical one is already present.
+pub(crate) fn push_predicate(predicates: &mut Vec<Meta>, predicate: &Meta) {
+    // NOTE: Predicates are compared syntactically, so `all(a, b)` and
+    // `all(b, a)` count as different predicates.
+    let predicate_string = predicate.to_token_stream().to_string();
+    if !predicates
+        .iter()
+        .any(|other| other.to_token_stream().to_string() == predicate_string)
+    {
+        predicates.push(predicate.clone());
+    }
+}
+
+/// A qualifier that only applies when a `cfg` predicate holds, e.g. `const if feature = "nightly"`
+/// or `pub if test else pub(crate)`.
+#[derive(Clone)]
+pub struct Condition {
+    pub qualifier: Qualifier,
+    pub predicate: Meta,
+    pub otherwise: Option<(Token![else], Qualifier)>,
+}
+
+impl ToTokens for Condition {
+    fn to_tokens(&self, tokens: &mut pm2::TokenStream) {
+        self.qualifier.to_tokens(tokens);
+        Token![if](self.qualifier.span()).to_tokens(tokens);
+        self.predicate.to_tokens(tokens);
+        if let Some((else_token, otherwise)) = &self.otherwise {
+            else_token.to_tokens(tokens);
+            otherwise.to_tokens(tokens);
+        }
+    }
+}
+
+/// A selection of fields in `#[field_qualifiers]`.
+#[cfg(feature = "full")]
+#[derive(Clone)]
+pub enum FieldSelector {
+    /// A field name, e.g. `x`, or `_0` for the first field of a tuple struct.
+    Name(Ident),
+    /// A tuple field index, e.g. `0`.
+    Index(LitInt),
+    /// A range of tuple field indices, e.g. `0..3` or `0..=2`.
+    Range {
+        start: LitInt,
+        limits: RangeLimits,
+        end: LitInt,
+    },
+    /// Every field, optionally except some, e.g. `*`, `* except secret`, or `* except (0, 1)`.
+    All {
+        star_token: Token![*],
+        except: Vec<FieldSelector>,
+    },
+    /// Every field of the given type, e.g. `: String`.
+    Type { colon_token: Token![:], ty: Type },
+}
+
+#[cfg(feature = "full")]
+impl FieldSelector {
+    /// Returns the span of the selector in the attribute.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Name(name) => name.span(),
+            Self::Index(index) => index.span(),
+            Self::Range { start, .. } => start.span(),
+            Self::All { star_token, .. } => star_token.span,
+            Self::Type { colon_token, .. } => colon_token.span,
+        }
+    }
+
+    /// Parses a field name or a tuple field index.
+    fn parse_single(input: ParseStream) -> syn::Result<Self> {
+        let lookahead = input.lookahead1();
+        if lookahead.peek(Ident) {
+            input.parse().map(Self::Name)
+        } else if lookahead.peek(LitInt) {
+            input.parse().map(Self::Index)
+        } else {
+            Err(lookahead.error())
+        }
+    }
+
+    /// Parses the type of a type selector up to the parenthesized qualifiers that follow it.
+    ///
+    /// Parsing the type directly would treat the qualifiers as the arguments of a
+    /// parenthesized path, e.g. `Fn(pub)`, so the tokens of the type are collected first.
+    fn parse_type(input: ParseStream) -> syn::Result<Type> {
+        let tokens = input.step(|cursor| {
+            let mut tokens = pm2::TokenStream::new();
+            let mut rest = *cursor;
+            let mut angle_depth = 0usize;
+            let mut after_minus = false;
+            while let Some((token_tree, next)) = rest.token_tree() {
+                match &token_tree {
+                    pm2::TokenTree::Group(group)
+                        if angle_depth == 0
+                            && group.delimiter() == pm2::Delimiter::Parenthesis
+                            && (next.eof()
+                                || next
+                                    .punct()
+                                    .map_or(false, |(punct, _)| punct.as_char() == ',')) =>
+                    {
+                        return Ok((tokens, rest));
+                    }
+                    pm2::TokenTree::Punct(punct) if punct.as_char() == '<' => angle_depth += 1,
+                    // `->` in function types doesn't close an angle bracket.
+                    pm2::TokenTree::Punct(punct) if punct.as_char() == '>' && !after_minus => {
+                        angle_depth = angle_depth.saturating_sub(1);
+                    }
+                    _ => {}
+                }
+                after_minus = matches!(
+                    &token_tree,
+                    pm2::TokenTree::Punct(punct)
+                        if punct.as_char() == '-' && punct.spacing() == pm2::Spacing::Joint
+                );
+                tokens.extend(std::iter::once(token_tree));
+                rest = next;
+            }
+            Err(cursor.error("expected a type followed by qualifiers in parentheses"))
+        })?;
+        syn::parse2(tokens)
+    }
+}
+
+#[cfg(feature = "full")]
+impl Parse for FieldSelector {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![*]) {
+            let star_token = input.parse()?;
+            let mut except = Vec::new();
+            if input.peek(kw::except) {
+                input.parse::<kw::except>()?;
+                // A parenthesized list of exceptions must be followed by the qualifiers.
+                if input.peek(Paren) && input.peek2(Paren) {
+                    let content;
+                    let _paren_token = parenthesized!(content in input);
+                    let selectors = content.parse_terminated(Self::parse_single, Token![,])?;
+                    except.extend(selectors);
+                } else {
+                    except.push(input.call(Self::parse_single)?);
+                }
+            }
+            Ok(Self::All { star_token, except })
+        } else if input.peek(Token![:]) {
+            Ok(Self::Type {
+                colon_token: input.parse()?,
+                ty: input.call(Self::parse_type)?,
+            })
+        } else if input.peek(LitInt) {
+            let start = input.parse()?;
+            if input.peek(Token![..]) {
+                Ok(Self::Range {
+                    start,
+                    limits: input.parse()?,
+                    end: input.parse()?,
+                })
+            } else {
+                Ok(Self::Index(start))
+            }
+        } else {
+            input.call(Self::parse_single)
+        }
+    }
+}
+
+/// A list of field selectors and the qualifiers to apply to them.
+#[cfg(feature = "full")]
+#[derive(Clone)]
+pub struct FieldQualifiers(pub Vec<(FieldSelector, Qualifiers)>);
+
+#[cfg(feature = "full")]
+impl Parse for FieldQualifiers {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut list = Vec::new();
+
+        while !input.is_empty() {
+            let selector = input.parse::<FieldSelector>()?;
+            let content;
+            let _paren_token = parenthesized!(content in input);
+            let qualifiers = content.parse::<Qualifiers>()?;
+            qualifiers.check_item_only()?;
+            list.push((selector, qualifiers));
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(Self(list))
+    }
+}
+
+#[cfg(feature = "full")]
+impl FieldQualifiers {
+    /// Returns the distinct `cfg` predicates of the conditional qualifiers.
+    pub fn predicates(&self) -> Vec<Meta> {
+        let mut predicates = Vec::new();
+        for (_, qualifiers) in &self.0 {
+            for predicate in qualifiers.predicates() {
+                push_predicate(&mut predicates, &predicate);
+            }
+        }
+        predicates
+    }
+
+    /// Returns the qualifiers that apply when exactly the predicates for which `holds` returns
+    /// `true` hold.
+    pub fn resolve(&self, holds: &dyn Fn(&Meta) -> bool) -> syn::Result<Self> {
+        self.0
+            .iter()
+            .map(|(selector, qualifiers)| Ok((selector.clone(), qualifiers.resolve(holds)?)))
+            .collect::<syn::Result<_>>()
+            .map(Self)
+    }
+}
+
+/// A list of member names and the qualifiers to apply to them.
+#[cfg(feature = "full")]
+#[derive(Clone)]
+pub struct MemberQualifiers(pub Vec<(Ident, Qualifiers)>);
+
+#[cfg(feature = "full")]
+impl Parse for MemberQualifiers {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut list = Vec::new();
+
+        while !input.is_empty() {
+            let name = input.parse::<Ident>()?;
+            let content;
+            let _paren_token = parenthesized!(content in input);
+            let qualifiers = content.parse::<Qualifiers>()?;
+            qualifiers.check_item_only()?;
+            list.push((name, qualifiers));
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(Self(list))
+    }
+}
+
+#[cfg(feature = "full")]
+impl MemberQualifiers {
+    /// Returns the distinct `cfg` predicates of the conditional qualifiers.
+    pub fn predicates(&self) -> Vec<Meta> {
+        let mut predicates = Vec::new();
+        for (_, qualifiers) in &self.0 {
+            for predicate in qualifiers.predicates() {
+                push_predicate(&mut predicates, &predicate);
+            }
+        }
+        predicates
+    }
+
+    /// Returns the qualifiers that apply when exactly the predicates for which `holds` returns
+    /// `true` hold.
+    pub fn resolve(&self, holds: &dyn Fn(&Meta) -> bool) -> syn::Result<Self> {
+        self.0
+            .iter()
+            .map(|(name, qualifiers)| Ok((name.clone(), qualifiers.resolve(holds)?)))
+            .collect::<syn::Result<_>>()
+            .map(Self)
+    }
+}
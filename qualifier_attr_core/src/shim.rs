@@ -0,0 +1,186 @@
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+    ext::IdentExt, parse_quote, punctuated::Punctuated, spanned::Spanned, FnArg, GenericArgument,
+    Generics, PathArguments, ReturnType, Token, Type, TypeReference,
+};
+
+use crate::{
+    export, ffi_check,
+    parse::{ExternShim, FlexibleItemFn, OnPanic},
+};
+
+/// Emits an exported wrapper with the given ABI that forwards to the function, which is left
+/// unchanged.
+///
+/// The wrapper catches panics unless it is given `panic = "abort"`, since catching them needs
+/// `std`, and converts `Option<&T>` to raw pointers at the boundary. It is hidden in an anonymous constant, so it doesn't take up a name.
+pub(crate) fn shim(function: &FlexibleItemFn, shim: &ExternShim) -> syn::Result<pm2::TokenStream> {
+    let sig = &function.sig;
+    let span = shim.extern_shim_token.span;
+
+    let mut errors = Vec::new();
+    if !sig.generics.params.is_empty() {
+        errors.push(syn::Error::new(
+            sig.generics.span(),
+            "`extern_shim` doesn't support generic functions",
+        ));
+    }
+    if let Some(async_token) = sig.asyncness {
+        errors.push(syn::Error::new(
+            async_token.span,
+            "`extern_shim` doesn't support `async` functions",
+        ));
+    }
+    if let Some(variadic) = &sig.variadic {
+        errors.push(syn::Error::new(
+            variadic.span(),
+            "`extern_shim` doesn't support variadic functions",
+        ));
+    }
+
+    // Whether the wrapper dereferences raw pointers, or calls an unsafe function.
+    let mut is_unsafe = sig.unsafety.is_some();
+    let mut inputs = Punctuated::<FnArg, Token![,]>::new();
+    let mut conversions = Vec::new();
+    let mut args = Vec::new();
+    for (i, input) in sig.inputs.iter().enumerate() {
+        let ty = match input {
+            FnArg::Receiver(receiver) => {
+                errors.push(syn::Error::new(
+                    receiver.span(),
+                    "`extern_shim` doesn't support methods; consider a free function instead",
+                ));
+                continue;
+            }
+            FnArg::Typed(pat_type) => &*pat_type.ty,
+        };
+        let arg = format_ident!("arg{}", i, span = pm2::Span::mixed_site());
+        let shim_ty: Type = if let Some(reference) = option_reference(ty) {
+            is_unsafe = true;
+            let elem = &reference.elem;
+            if reference.mutability.is_some() {
+                conversions.push(quote!(let #arg = unsafe { #arg.as_mut() };));
+                parse_quote!(*mut #elem)
+            } else {
+                conversions.push(quote!(let #arg = unsafe { #arg.as_ref() };));
+                parse_quote!(*const #elem)
+            }
+        } else {
+            ty.clone()
+        };
+        inputs.push(parse_quote!(#arg: #shim_ty));
+        args.push(arg);
+    }
+
+    let value = pm2::Ident::new("value", pm2::Span::mixed_site());
+    let (output, conversion): (ReturnType, _) = match &sig.output {
+        ReturnType::Type(_, ty) => match option_reference(ty) {
+            Some(reference) => {
+                let elem = &reference.elem;
+                if reference.mutability.is_some() {
+                    (
+                        parse_quote!(-> *mut #elem),
+                        quote!(#value.map_or(::core::ptr::null_mut(), |#value| #value as *mut #elem)),
+                    )
+                } else {
+                    (
+                        parse_quote!(-> *const #elem),
+                        quote!(#value.map_or(::core::ptr::null(), |#value| #value as *const #elem)),
+                    )
+                }
+            }
+            None => (sig.output.clone(), quote!(#value)),
+        },
+        ReturnType::Default => (ReturnType::Default, quote!(#value)),
+    };
+
+    if shim.unchecked.is_none() {
+        let mut ffi_errors = ffi_check::find_unsafe_types(&Generics::default(), &inputs, &output);
+        if !ffi_errors.is_empty() {
+            errors.push(syn::Error::new(
+                span,
+                "cannot emit an extern shim for this function; use `extern_shim(\"ABI\", unchecked)` to skip this check",
+            ));
+            errors.append(&mut ffi_errors);
+        }
+    }
+
+    if let Some(error) = errors.into_iter().reduce(|mut error, next| {
+        error.combine(next);
+        error
+    }) {
+        return Err(error);
+    }
+
+    let ident = &sig.ident;
+    let shim_ident = format_ident!("{}_shim", ident.unraw());
+    let name = match &shim.name {
+        Some(name) => name.value(),
+        None => ident.unraw().to_string(),
+    };
+    let abi = &shim.abi;
+    let unsafety = if is_unsafe {
+        Some(Token![unsafe](span))
+    } else {
+        None
+    };
+    let call = match sig.unsafety {
+        Some(_) => quote!(unsafe { #ident(#(#args),*) }),
+        None => quote!(#ident(#(#args),*)),
+    };
+    // Without `std`, panics can't be caught, and unwinding out of a foreign ABI aborts anyway.
+    let body = match (&shim.panic_abort, &shim.on_panic) {
+        (Some(_), _) => quote! {
+            let #value = #call;
+            #conversion
+        },
+        (None, on_panic) => {
+            let on_panic = match on_panic {
+                OnPanic::Abort => quote!(::std::process::abort()),
+                OnPanic::Return(expr) => quote!(#expr),
+            };
+            quote! {
+                match ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(move || #call)) {
+                    ::core::result::Result::Ok(#value) => #conversion,
+                    ::core::result::Result::Err(_) => #on_panic,
+                }
+            }
+        }
+    };
+
+    let export_name =
+        export::symbol_attribute(&name, &shim_ident, span, shim.unsafe_token.is_some());
+
+    Ok(quote_spanned! {span=>
+        const _: () = {
+            #export_name
+            #[allow(unused_unsafe)]
+            #unsafety #abi fn #shim_ident(#inputs) #output {
+                #(#conversions)*
+                #body
+            }
+        };
+    })
+}
+
+/// Returns the reference in `Option<&T>` or `Option<&mut T>`.
+fn option_reference(ty: &Type) -> Option<&TypeReference> {
+    let segment = match ty {
+        Type::Path(type_path) if type_path.qself.is_none() => type_path.path.segments.last()?,
+        Type::Paren(type_paren) => return option_reference(&type_paren.elem),
+        Type::Group(type_group) => return option_reference(&type_group.elem),
+        _ => return None,
+    };
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) if arguments.args.len() == 1 => {
+            match arguments.args.first() {
+                Some(GenericArgument::Type(Type::Reference(reference))) => Some(reference),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,114 @@
+use quote::quote_spanned;
+use syn::{
+    parse_quote_spanned,
+    visit_mut::{self, VisitMut},
+    Block, Expr, ExprAsync, ExprBlock, Item, Macro,
+};
+
+/// Removes every `.await` from the body of a function that is no longer `async`.
+///
+/// Async blocks that are awaited right away become immediately called closures, so `return`
+/// and `?` keep their meaning, and async closures become plain closures, so awaiting their
+/// results works after stripping too. Anything else that would still produce a future, such
+/// as an async block that is stored or passed along, is reported instead.
+pub(crate) fn strip_await(body: &mut Block, errors: &mut Vec<syn::Error>) {
+    let mut stripper = AwaitStripper { errors };
+    stripper.visit_block_mut(body);
+}
+
+struct AwaitStripper<'a> {
+    errors: &'a mut Vec<syn::Error>,
+}
+
+impl VisitMut for AwaitStripper<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Await(expr_await) => {
+                let base = std::mem::replace(&mut *expr_await.base, Expr::PLACEHOLDER);
+                *expr = match base {
+                    Expr::Async(expr_async) => call_async_block(expr_async),
+                    base => base,
+                };
+            }
+            Expr::Async(expr_async) => {
+                self.errors.push(syn::Error::new(
+                    expr_async.async_token.span,
+                    "cannot strip `.await` from an async block that isn't awaited right away",
+                ));
+                return;
+            }
+            Expr::Closure(expr_closure) => {
+                expr_closure.asyncness = None;
+                // `|| async move { ... }` is the closure equivalent of an async closure.
+                let body = std::mem::replace(&mut *expr_closure.body, Expr::PLACEHOLDER);
+                *expr_closure.body = match body {
+                    Expr::Async(ExprAsync {
+                        attrs,
+                        capture,
+                        block,
+                        ..
+                    }) => {
+                        expr_closure.capture = expr_closure.capture.or(capture);
+                        Expr::Block(ExprBlock {
+                            attrs,
+                            label: None,
+                            block,
+                        })
+                    }
+                    body => body,
+                };
+            }
+            _ => {}
+        }
+        visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_item_mut(&mut self, _item: &mut Item) {
+        // Nested items have their own asyncness.
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut Macro) {
+        if let Some(span) = find_await(mac.tokens.clone()) {
+            self.errors.push(syn::Error::new(
+                span,
+                "cannot strip `.await` inside a macro invocation",
+            ));
+        }
+    }
+}
+
+/// Turns an awaited async block into an immediately called closure.
+fn call_async_block(expr_async: ExprAsync) -> Expr {
+    let ExprAsync {
+        attrs,
+        capture,
+        block,
+        ..
+    } = expr_async;
+    // Marks the call as macro output, which lints such as `clippy::redundant_closure_call`
+    // leave alone.
+    let span = pm2::Span::call_site();
+    let closure = quote_spanned!(span=> #capture || #block);
+    parse_quote_spanned!(span=> #(#attrs)* (#closure)())
+}
+
+/// Returns the span of the first `.await` in the tokens, if any.
+fn find_await(tokens: pm2::TokenStream) -> Option<pm2::Span> {
+    let mut after_dot = false;
+    for token in tokens {
+        match token {
+            pm2::TokenTree::Group(group) => {
+                if let Some(span) = find_await(group.stream()) {
+                    return Some(span);
+                }
+                after_dot = false;
+            }
+            pm2::TokenTree::Punct(punct) => after_dot = punct.as_char() == '.',
+            pm2::TokenTree::Ident(ident) if after_dot && ident == "await" => {
+                return Some(ident.span());
+            }
+            _ => after_dot = false,
+        }
+    }
+    None
+}
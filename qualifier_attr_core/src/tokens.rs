@@ -0,0 +1,429 @@
+use pm2::{Delimiter, Span, TokenStream, TokenTree};
+use quote::ToTokens;
+
+use crate::parse::{Async, Change, Const, Extern, Qualifiers};
+
+/// The kind of an item, which decides what qualifiers it has.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Const,
+    Enum,
+    ExternCrate,
+    Fn,
+    ForeignMod,
+    Impl,
+    Mod,
+    Static,
+    Struct,
+    Trait,
+    TraitAlias,
+    Type,
+    Union,
+    Use,
+    /// Macro invocations, and anything else that the header isn't understood for.
+    Other,
+}
+
+/// The tokens of an item, with the qualifiers in its header split into slots.
+///
+/// Everything from the keyword of the item on is kept as it is, except for the `mut` of statics,
+/// so bodies are never parsed.
+struct Header {
+    attrs: Vec<TokenTree>,
+    visibility: Option<TokenStream>,
+    defaultness: Option<TokenStream>,
+    constness: Option<TokenStream>,
+    asyncness: Option<TokenStream>,
+    unsafety: Option<TokenStream>,
+    safety: Option<TokenStream>,
+    autoness: Option<TokenStream>,
+    abi: Option<TokenStream>,
+    /// The `extern` of a foreign module, which stays when its ABI is removed.
+    extern_token: Option<TokenTree>,
+    kind: Kind,
+    keyword: Vec<TokenTree>,
+    mutability: Option<TokenStream>,
+    rest: Vec<TokenTree>,
+}
+
+impl Header {
+    /// Splits an item at the qualifiers before its keyword, which it is classified by.
+    fn scan(input: TokenStream) -> Self {
+        let tokens = input.into_iter().collect::<Vec<_>>();
+        let mut header = Self {
+            attrs: Vec::new(),
+            visibility: None,
+            defaultness: None,
+            constness: None,
+            asyncness: None,
+            unsafety: None,
+            safety: None,
+            autoness: None,
+            abi: None,
+            extern_token: None,
+            kind: Kind::Other,
+            keyword: Vec::new(),
+            mutability: None,
+            rest: Vec::new(),
+        };
+        let mut i = 0;
+
+        while is_punct(tokens.get(i), '#') && is_group(tokens.get(i + 1), Delimiter::Bracket) {
+            header.attrs.extend_from_slice(&tokens[i..i + 2]);
+            i += 2;
+        }
+
+        if is_ident(tokens.get(i), "pub") {
+            let end = if is_group(tokens.get(i + 1), Delimiter::Parenthesis) {
+                i + 2
+            } else {
+                i + 1
+            };
+            header.visibility = Some(tokens[i..end].iter().cloned().collect());
+            i = end;
+        }
+
+        loop {
+            let slot = match ident(tokens.get(i)).as_deref() {
+                Some("async") => &mut header.asyncness,
+                Some("auto") => &mut header.autoness,
+                Some("default") => &mut header.defaultness,
+                Some("safe") => &mut header.safety,
+                Some("unsafe") => &mut header.unsafety,
+                // `const` is a qualifier of functions, and the keyword of constants.
+                Some("const")
+                    if ["async", "extern", "fn", "safe", "unsafe"]
+                        .iter()
+                        .any(|qualifier| is_ident(tokens.get(i + 1), qualifier)) =>
+                {
+                    &mut header.constness
+                }
+                Some("extern") if !is_ident(tokens.get(i + 1), "crate") => {
+                    if header.abi.is_some() {
+                        break;
+                    }
+                    let end = match tokens.get(i + 1) {
+                        Some(TokenTree::Literal(_)) => i + 2,
+                        _ => i + 1,
+                    };
+                    header.abi = Some(tokens[i..end].iter().cloned().collect());
+                    header.extern_token = Some(tokens[i].clone());
+                    i = end;
+                    continue;
+                }
+                _ => break,
+            };
+            // Repeated qualifiers are left for the compiler to report.
+            if slot.is_some() {
+                break;
+            }
+            *slot = Some(tokens[i].clone().into());
+            i += 1;
+        }
+
+        let (kind, len) = match ident(tokens.get(i)).as_deref() {
+            Some("const") => (Kind::Const, 1),
+            Some("enum") => (Kind::Enum, 1),
+            Some("extern") => (Kind::ExternCrate, 2),
+            Some("fn") => (Kind::Fn, 1),
+            Some("impl") => (Kind::Impl, 1),
+            Some("mod") => (Kind::Mod, 1),
+            Some("static") => (Kind::Static, 1),
+            Some("struct") => (Kind::Struct, 1),
+            Some("trait") if is_group(tokens.last(), Delimiter::Brace) => (Kind::Trait, 1),
+            Some("trait") => (Kind::TraitAlias, 1),
+            Some("type") => (Kind::Type, 1),
+            Some("union") if ident(tokens.get(i + 1)).is_some() => (Kind::Union, 1),
+            Some("use") => (Kind::Use, 1),
+            None if header.abi.is_some() && is_group(tokens.get(i), Delimiter::Brace) => {
+                (Kind::ForeignMod, 0)
+            }
+            _ => (Kind::Other, 0),
+        };
+        header.kind = kind;
+        header.keyword = tokens[i..i + len].to_vec();
+        i += len;
+
+        if kind == Kind::Static && is_ident(tokens.get(i), "mut") {
+            header.mutability = Some(tokens[i].clone().into());
+            i += 1;
+        }
+        header.rest = tokens[i..].to_vec();
+
+        header
+    }
+
+    /// Returns whether the item has a body or a value, which foreign items don't.
+    fn has_body(&self) -> bool {
+        match self.kind {
+            Kind::Static => self.rest.iter().any(|token| is_punct(Some(token), '=')),
+            _ => is_group(self.rest.last(), Delimiter::Brace),
+        }
+    }
+}
+
+impl ToTokens for Header {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(self.attrs.iter().cloned());
+        for slot in [
+            &self.visibility,
+            &self.defaultness,
+            &self.constness,
+            &self.asyncness,
+            &self.unsafety,
+            &self.safety,
+            &self.autoness,
+            &self.abi,
+        ] {
+            tokens.extend(slot.iter().cloned());
+        }
+        tokens.extend(self.keyword.iter().cloned());
+        tokens.extend(self.mutability.iter().cloned());
+        tokens.extend(self.rest.iter().cloned());
+    }
+}
+
+/// Applies qualifiers to an item by editing the qualifiers in its header, without parsing the
+/// rest of the item.
+///
+/// Returns `None` for the qualifiers and items that the `syn` backend has to handle, because they
+/// check or rewrite more than the header. Without the `full` feature, those qualifiers are
+/// reported instead, and `const` and `extern` are applied without checking the function.
+pub(crate) fn qualify(
+    qualifiers: &Qualifiers,
+    input: &TokenStream,
+) -> syn::Result<Option<TokenStream>> {
+    if let Some((span, name)) = needs_syn(qualifiers) {
+        return fallback(span, name);
+    }
+
+    let mut header = Header::scan(input.clone());
+    let kind = header.kind;
+    let has_body = header.has_body();
+
+    if cfg!(feature = "full") {
+        let checks_const = matches!(
+            &qualifiers.constness,
+            Some(Change::Set(Const {
+                unchecked: None,
+                ..
+            }))
+        );
+        let checks_abi = matches!(
+            &qualifiers.abi,
+            Some(Change::Set(Extern {
+                unchecked: None,
+                ..
+            }))
+        );
+        if kind == Kind::Other || kind == Kind::Fn && (checks_const && has_body || checks_abi) {
+            return Ok(None);
+        }
+    }
+
+    let has_visibility = !matches!(kind, Kind::ForeignMod | Kind::Impl | Kind::Other);
+    let has_defaultness = matches!(kind, Kind::Const | Kind::Fn | Kind::Impl | Kind::Type);
+    let has_unsafety = match kind {
+        Kind::Fn | Kind::ForeignMod | Kind::Impl | Kind::Mod | Kind::Trait => true,
+        Kind::Static => !has_body,
+        _ => false,
+    };
+    // Only foreign functions and statics, which have no body, can be `safe`.
+    let has_safety = matches!(kind, Kind::Fn | Kind::Static) && !has_body;
+
+    let mut unsafety = qualifiers.unsafety.clone();
+    let mut safety = qualifiers.safety.clone();
+    // `safe` and `unsafe` replace each other on items that support both.
+    if has_unsafety && has_safety {
+        match (&safety, &unsafety) {
+            (Some(Change::Set(safe_token)), None) => {
+                unsafety = Some(Change::Clear(safe_token.span));
+            }
+            (None, Some(Change::Set(unsafe_token))) => {
+                safety = Some(Change::Clear(unsafe_token.span));
+            }
+            _ => {}
+        }
+    }
+
+    let exact = qualifiers.exact.is_some();
+    let mut errors = Vec::new();
+
+    apply_change(
+        Some(&mut header.visibility).filter(|_| has_visibility),
+        qualifiers.visibility.clone(),
+        exact,
+        "visibility",
+        &mut errors,
+    );
+    apply_change(
+        Some(&mut header.defaultness).filter(|_| has_defaultness),
+        qualifiers.defaultness.clone(),
+        exact,
+        "defaultness",
+        &mut errors,
+    );
+    apply_change(
+        Some(&mut header.constness).filter(|_| kind == Kind::Fn),
+        qualifiers
+            .constness
+            .clone()
+            .map(|constness| constness.map(|const_qualifier| const_qualifier.const_token)),
+        exact,
+        "constness",
+        &mut errors,
+    );
+    apply_change(
+        Some(&mut header.asyncness).filter(|_| kind == Kind::Fn),
+        qualifiers
+            .asyncness
+            .clone()
+            .map(|asyncness| asyncness.map(|async_qualifier| async_qualifier.async_token)),
+        exact,
+        "asyncness",
+        &mut errors,
+    );
+    apply_change(
+        Some(&mut header.unsafety).filter(|_| has_unsafety),
+        unsafety,
+        exact,
+        "unsafety",
+        &mut errors,
+    );
+    apply_change(
+        Some(&mut header.abi).filter(|_| matches!(kind, Kind::Fn | Kind::ForeignMod)),
+        qualifiers
+            .abi
+            .clone()
+            .map(|abi| abi.map(|extern_qualifier| extern_qualifier.abi)),
+        exact,
+        "ABI",
+        &mut errors,
+    );
+    // The ABI of an `extern` block falls back to `extern "C"` when cleared.
+    if kind == Kind::ForeignMod && header.abi.is_none() {
+        header.abi = header.extern_token.clone().map(TokenStream::from);
+    }
+    apply_change(
+        Some(&mut header.mutability).filter(|_| kind == Kind::Static),
+        qualifiers.mutability.clone(),
+        exact,
+        "mutability",
+        &mut errors,
+    );
+    apply_change(
+        Some(&mut header.safety).filter(|_| has_safety),
+        safety,
+        exact,
+        "safety",
+        &mut errors,
+    );
+    apply_change(
+        Some(&mut header.autoness).filter(|_| kind == Kind::Trait),
+        qualifiers.autoness.clone(),
+        exact,
+        "autoness",
+        &mut errors,
+    );
+
+    if let Some(error) = errors.into_iter().reduce(|mut error, next| {
+        error.combine(next);
+        error
+    }) {
+        return Err(error);
+    }
+    if kind == Kind::Other {
+        return Ok(Some(input.clone()));
+    }
+    Ok(Some(header.into_token_stream()))
+}
+
+/// Returns the span and the name of the first qualifier that checks or rewrites more than the
+/// header of an item.
+fn needs_syn(qualifiers: &Qualifiers) -> Option<(Span, &'static str)> {
+    if let Some(each) = &qualifiers.each {
+        return Some((each.each_token.span, "`each`"));
+    }
+    if let Some(dual) = &qualifiers.dual {
+        return Some((dual.dual_token.span, "`dual`"));
+    }
+    if let Some(extern_shim) = &qualifiers.extern_shim {
+        return Some((extern_shim.extern_shim_token.span, "`extern_shim`"));
+    }
+    if let Some(export) = &qualifiers.export {
+        return Some((export.export_token.span, "`export`"));
+    }
+    if let Some(strip_await_token) = &qualifiers.strip_await {
+        return Some((strip_await_token.span, "`!async(strip_await)`"));
+    }
+    if let Some(Change::Set(Async {
+        async_token,
+        desugar: Some(_),
+    })) = &qualifiers.asyncness
+    {
+        return Some((async_token.span, "desugaring `async`"));
+    }
+    if let Some(Change::Set(Extern {
+        header: Some(header),
+        ..
+    })) = &qualifiers.abi
+    {
+        return Some((header.span(), "`header`"));
+    }
+    // Traits are checked before they become auto traits.
+    if let Some(Change::Set(auto_token)) = &qualifiers.autoness {
+        return Some((auto_token.span, "`auto`"));
+    }
+    None
+}
+
+/// Leaves the item to the `syn` backend, or reports the qualifier if it isn't compiled in.
+fn fallback(span: Span, name: &str) -> syn::Result<Option<TokenStream>> {
+    if cfg!(feature = "full") {
+        Ok(None)
+    } else {
+        Err(syn::Error::new(
+            span,
+            format!("{} requires the `full` feature of `qualifier_attr`", name),
+        ))
+    }
+}
+
+/// Applies a single qualifier change to its slot, like the `syn` backend does.
+fn apply_change<T: ToTokens>(
+    target: Option<&mut Option<TokenStream>>,
+    change: Option<Change<T>>,
+    exact: bool,
+    name: &str,
+    errors: &mut Vec<syn::Error>,
+) {
+    match (target, change) {
+        (Some(target), Some(Change::Set(value))) => *target = Some(value.into_token_stream()),
+        (Some(target), Some(Change::Clear(_))) => *target = None,
+        (Some(target), None) if exact => *target = None,
+        (Some(_), None) | (None, None) => {}
+        (None, Some(change)) => errors.push(syn::Error::new(
+            change.span(),
+            format!("{} qualifier not supported here", name),
+        )),
+    }
+}
+
+fn ident(token: Option<&TokenTree>) -> Option<String> {
+    match token {
+        Some(TokenTree::Ident(ident)) => Some(ident.to_string()),
+        _ => None,
+    }
+}
+
+fn is_ident(token: Option<&TokenTree>, name: &str) -> bool {
+    ident(token).as_deref() == Some(name)
+}
+
+fn is_punct(token: Option<&TokenTree>, ch: char) -> bool {
+    matches!(token, Some(TokenTree::Punct(punct)) if punct.as_char() == ch)
+}
+
+fn is_group(token: Option<&TokenTree>, delimiter: Delimiter) -> bool {
+    matches!(token, Some(TokenTree::Group(group)) if group.delimiter() == delimiter)
+}
@@ -0,0 +1,282 @@
+#![cfg(feature = "full")]
+
+use qualifier_attr_core::{apply_qualifiers, expand, Qualifiers, QualifiersLens, Qualify};
+use quote::{quote, ToTokens};
+use syn::{parse_quote, Item, Visibility};
+
+// A custom item type with a visibility, like a field of a macro's own input.
+struct Entry {
+    vis: Visibility,
+}
+
+impl Qualify for Entry {
+    fn qualify(&mut self) -> QualifiersLens<'_> {
+        QualifiersLens::new().visibility(&mut self.vis)
+    }
+}
+
+#[test]
+fn public_api() {
+    // Qualifiers print back into a list that parses into the same qualifiers.
+    let qualifiers: Qualifiers = parse_quote! {
+        exact, pub(crate), const(unchecked), !async(strip_await), extern "C"(unchecked, unchecked_abi),
+        unsafe if feature = "ffi" else !unsafe
+    };
+    let reparsed: Qualifiers = syn::parse2(qualifiers.to_token_stream()).unwrap();
+    assert_eq!(
+        reparsed.to_token_stream().to_string(),
+        qualifiers.to_token_stream().to_string(),
+    );
+
+    // `export(unsafe)` wraps the attribute that exports the function in `unsafe(...)`.
+    let output = expand::qualifiers(
+        quote!(export(unsafe, name = "answer_c")),
+        quote!(
+            fn answer() -> u32 {
+                42
+            }
+        ),
+    )
+    .to_string();
+    assert!(output.contains("unsafe (export_name = \"answer_c\")"));
+    let reparsed: Qualifiers = syn::parse2(quote!(export(unsafe, name = "answer_c"))).unwrap();
+    assert_eq!(
+        reparsed.to_token_stream().to_string(),
+        quote!(export(unsafe, name = "answer_c")).to_string(),
+    );
+
+    // Version gates report a missing build script, which would leave them never applying.
+    let output = expand::qualifiers(
+        quote!(const(since = "1.61")),
+        quote!(
+            fn answer() -> u32 {
+                42
+            }
+        ),
+    )
+    .to_string();
+    assert!(output.contains("cfg (not (qualifier_attr_build))"));
+
+    // `exact` changes how the whole list applies, so it has to come first.
+    assert!(syn::parse2::<Qualifiers>(quote!(const, exact)).is_err());
+
+    // Qualifiers built by hand may only clash for some combinations of predicates, which is
+    // reported when they are resolved.
+    let mut qualifiers: Qualifiers = parse_quote!(pub);
+    let conditional: Qualifiers = parse_quote!(pub(crate) if feature = "internal");
+    qualifiers.conditions = conditional.conditions;
+    assert!(qualifiers.resolve(&|_| false).is_ok());
+    assert!(qualifiers.resolve(&|_| true).is_err());
+
+    // `unchecked` skips the check of the types of the function, and `unchecked_abi` the check of
+    // the name of the ABI.
+    assert!(syn::parse2::<Qualifiers>(quote!(extern "x86-interrupt"(unchecked))).is_err());
+    assert!(syn::parse2::<Qualifiers>(quote!(extern "x86-interrupt"(unchecked_abi))).is_ok());
+
+    // Wrappers that don't catch panics can't return a value on panic.
+    assert!(syn::parse2::<Qualifiers>(quote!(extern_shim("C", panic = "abort"))).is_ok());
+    assert!(syn::parse2::<Qualifiers>(quote!(extern_shim("C", panic = "unwind"))).is_err());
+    assert!(
+        syn::parse2::<Qualifiers>(quote!(extern_shim("C", panic = "abort", on_panic = -1)))
+            .is_err()
+    );
+
+    let qualifiers: Qualifiers = parse_quote!(pub, const);
+    let mut item: Item = parse_quote!(
+        unsafe fn answer() -> u32 {
+            42
+        }
+    );
+    apply_qualifiers(&mut item, &qualifiers).unwrap();
+    assert_eq!(
+        item.to_token_stream().to_string(),
+        quote!(
+            pub const unsafe fn answer() -> u32 {
+                42
+            }
+        )
+        .to_string(),
+    );
+
+    // Qualifiers that emit other items, and unresolved conditions, are reported.
+    let qualifiers: Qualifiers = parse_quote!(dual(async, suffix = "_async"));
+    assert!(apply_qualifiers(&mut item, &qualifiers).is_err());
+    let qualifiers: Qualifiers = parse_quote!(const if feature = "nightly");
+    assert!(apply_qualifiers(&mut item, &qualifiers).is_err());
+
+    // Bodies made `const` are only rejected for what can't be `const` whatever the names refer to.
+    let qualifiers: Qualifiers = parse_quote!(const);
+    let mut item: Item = parse_quote!(
+        fn total(values: &[u32]) -> u32 {
+            let doubled = Meters(values[0]).map(|x| x * 2);
+            let _: &dyn Debug = &doubled;
+            doubled.into()
+        }
+    );
+    apply_qualifiers(&mut item, &qualifiers).unwrap();
+    let mut item: Item = parse_quote!(
+        fn total(values: &[u32]) -> u32 {
+            for value in values {}
+            Default::default()
+        }
+    );
+    let error = apply_qualifiers(&mut item, &qualifiers).unwrap_err();
+    assert_eq!(error.into_iter().count(), 3);
+
+    let mut entry = Entry {
+        vis: Visibility::Inherited,
+    };
+    let qualifiers: Qualifiers = parse_quote!(pub);
+    entry.qualify().apply(qualifiers).unwrap();
+    assert!(matches!(entry.vis, Visibility::Public(_)));
+    let qualifiers: Qualifiers = parse_quote!(const);
+    assert!(entry.qualify().apply(qualifiers).is_err());
+
+    // Overlapping field selectors can't give a field different visibilities.
+    let output = expand::field_qualifiers(
+        quote!(*(pub), x(pub(crate))),
+        quote! {
+            struct Point {
+                x: i32,
+                y: i32,
+            }
+        },
+    )
+    .to_string();
+    assert!(output.contains("field `x` is made `pub(crate)` here, but `pub` by another selector"));
+
+    // Selectors that select the same fields again are reported, as are fields that are given
+    // another qualifier by more than one selector.
+    for (selectors, message) in [
+        (quote!(*(pub), *(pub)), "`*` is already selected"),
+        (
+            quote!(: i32(pub), : i32(pub)),
+            "`: i32` is already selected",
+        ),
+        (
+            quote!(*(exact, pub), x(exact, pub)),
+            "field `x` is already given `exact` by another selector",
+        ),
+    ] {
+        let output = expand::field_qualifiers(
+            selectors,
+            quote! {
+                struct Point {
+                    x: i32,
+                    y: i32,
+                }
+            },
+        )
+        .to_string();
+        assert!(output.contains(message), "{}", output);
+    }
+
+    // Conditional helper attributes are expanded in place, without naming `qualifier_attr`, so
+    // that the expansions work for renamed dependencies and other procedural macros.
+    let output = expand::field_qualifiers(
+        quote!(),
+        quote! {
+            struct Config {
+                #[cfg_attr(feature = "expose", qualify(pub))]
+                secret: String,
+            }
+        },
+    )
+    .to_string();
+    assert!(output.contains("cfg (all (feature = \"expose\"))"));
+    assert!(output.contains("cfg (all (not (feature = \"expose\")))"));
+    assert!(!output.contains("qualifier_attr"));
+    assert!(!output.contains("qualify"));
+
+    // Each field is gated on its own conditions, so fields behind different features don't copy
+    // the whole struct.
+    let output = expand::field_qualifiers(
+        quote!(id(pub if feature = "ids")),
+        quote! {
+            struct Dto {
+                id: u64,
+                #[cfg_attr(feature = "a", qualify(pub))]
+                a: u32,
+                #[cfg_attr(feature = "b", qualify(pub))]
+                b: u32,
+                #[cfg_attr(feature = "c", qualify(pub))]
+                c: u32,
+                #[cfg_attr(feature = "d", qualify(pub))]
+                d: u32,
+                #[cfg_attr(feature = "e", qualify(pub))]
+                e: u32,
+                #[cfg_attr(feature = "f", qualify(pub))]
+                f: u32,
+                #[cfg_attr(feature = "g", qualify(pub))]
+                g: u32,
+            }
+        },
+    )
+    .to_string();
+    assert!(!output.contains("compile_error"));
+    assert_eq!(output.matches("struct Dto").count(), 1);
+    assert!(output.contains("# [cfg (all (feature = \"ids\"))] pub id : u64"));
+    assert!(output.contains("# [cfg (all (not (feature = \"g\")))] g : u32"));
+
+    // The members of a block that are made `async` share an executor bound.
+    let output = expand::qualify_members(
+        quote!(),
+        quote! {
+            impl Client {
+                #[qualify(async(send))]
+                fn fetch(&self) -> u32 { 42 }
+                #[cfg_attr(feature = "rt", qualify(async(desugar)))]
+                fn poll(&self) -> u32 { 42 }
+            }
+        },
+    )
+    .to_string();
+    assert!(!output.contains("qualifier_attr"));
+    assert_eq!(output.matches("compile_error").count(), 1);
+    assert!(output.contains("should be too"));
+
+    // Members are gated on their own conditions as well, rather than copying the whole block.
+    let output = expand::qualify_members(
+        quote!(),
+        quote! {
+            impl Api {
+                #[cfg_attr(feature = "a", qualify(pub))]
+                fn a() {}
+                #[cfg_attr(feature = "b", qualify(pub))]
+                fn b() {}
+                #[cfg_attr(feature = "c", qualify(pub))]
+                fn c() {}
+                #[cfg_attr(feature = "d", qualify(pub))]
+                fn d() {}
+                #[cfg_attr(feature = "e", qualify(pub))]
+                fn e() {}
+                #[cfg_attr(feature = "f", qualify(pub))]
+                fn f() {}
+                #[qualify(const if feature = "g")]
+                fn g() {}
+            }
+        },
+    )
+    .to_string();
+    assert!(!output.contains("compile_error"));
+    assert_eq!(output.matches("impl Api").count(), 1);
+    assert!(output.contains("# [cfg (all (feature = \"a\"))] pub fn a"));
+    assert!(output.contains("# [cfg (all (feature = \"g\"))] const fn g"));
+}
+
+#[test]
+fn desugared_lifetimes_are_early_bound() {
+    // The elided lifetimes are named and bounded in the where clause, which makes them early-bound,
+    // so the function no longer coerces to `for<'a> fn(&'a [T]) -> _`.
+    let output = expand::qualifiers(
+        quote!(async(desugar)),
+        quote!(
+            async fn first<T: Copy>(values: &[T]) -> T {
+                values[0]
+            }
+        ),
+    )
+    .to_string();
+    assert!(output.contains("fn first < '__future , '__life0 , T : Copy >"));
+    assert!(output.contains("where & '__life0 [T] : '__future"));
+}
@@ -0,0 +1,133 @@
+#![cfg(feature = "full")]
+
+use std::fs;
+
+use qualifier_attr_core::expand;
+use quote::quote;
+
+#[test]
+fn header() {
+    // `header` writes to `OUT_DIR`, where `qualifier_attr_build::emit_cfgs` records the cfgs of
+    // the crate.
+    let out_dir =
+        std::env::temp_dir().join(format!("qualifier_attr_header_{}", std::process::id()));
+    fs::create_dir_all(&out_dir).unwrap();
+    fs::write(
+        out_dir.join("qualifier_attr_cfgs"),
+        "feature=\"my-feature\"\nfeature=\"std\"\nunix",
+    )
+    .unwrap();
+    std::env::set_var("OUT_DIR", &out_dir);
+
+    let expansions = [
+        expand::qualifiers(
+            quote!(pub, extern "C"(header = "plugin.h")),
+            quote! {
+                #[no_mangle]
+                fn plugin_add(a: i32, b: u64) -> i64 {
+                    a as i64 + b as i64
+                }
+            },
+        ),
+        expand::qualifiers(
+            quote!(pub, extern "C"(header = "plugin.h")),
+            quote! {
+                #[no_mangle]
+                fn plugin_name() -> *const c_char {
+                    b"plugin\0".as_ptr().cast()
+                }
+            },
+        ),
+        expand::qualifiers(
+            quote!(pub, extern "C"(header = "plugin.h")),
+            quote! {
+                #[export_name = "plugin_norm"]
+                fn norm(point: &Point, out: Option<&mut f64>, _: *mut *const c_void) -> bool {
+                    true
+                }
+            },
+        ),
+        expand::qualifiers(
+            quote!(pub, extern "C"(unchecked, header = "plugin.h")),
+            quote! {
+                #[unsafe(export_name = "plugin_free")]
+                fn free(_data: Option<NonNull<u8>>, _len: usize) {}
+            },
+        ),
+        expand::qualifiers(
+            quote!(pub, export(prefix = "plugin_"), extern "C"(header = "plugin.h")),
+            quote!(
+                fn version() -> u32 {
+                    1
+                }
+            ),
+        ),
+        // Only the combination of conditions that is built writes to the header.
+        expand::qualifiers(
+            quote!(pub, extern "C"(header = "plugin.h") if all(unix, feature = "std")),
+            quote!(
+                #[no_mangle]
+                fn plugin_std() {}
+            ),
+        ),
+        // Features are compared by their real names, where `-` and `_` differ.
+        expand::qualifiers(
+            quote!(pub, extern "C"(header = "plugin.h") if feature = "my-feature"),
+            quote!(
+                #[no_mangle]
+                fn plugin_dashed() {}
+            ),
+        ),
+        expand::qualifiers(
+            quote!(pub, extern "C"(header = "plugin.h") if feature = "my_feature" else extern "C"),
+            quote!(
+                #[no_mangle]
+                fn plugin_underscored() {}
+            ),
+        ),
+        expand::qualifiers(
+            quote!(pub, extern "C"(header = "plugin.h") if feature = "ffi" else extern "C"),
+            quote!(
+                #[no_mangle]
+                fn plugin_ffi() {}
+            ),
+        ),
+    ];
+    for expansion in &expansions {
+        assert!(!expansion.to_string().contains("compile_error"));
+    }
+
+    // Functions with mangled symbols can't be linked to from C.
+    let mangled = expand::qualifiers(
+        quote!(pub, extern "C"(header = "plugin.h")),
+        quote!(
+            fn plugin_mangled() {}
+        ),
+    );
+    assert!(mangled
+        .to_string()
+        .contains("symbol of this function is mangled"));
+
+    let header = fs::read_to_string(out_dir.join("plugin.h")).unwrap();
+    assert!(header.contains("#ifndef PLUGIN_H"));
+    assert!(header.contains("#include <stdint.h>"));
+    assert!(header.contains("int64_t plugin_add(int32_t a, uint64_t b);\n"));
+    assert!(header.contains("const char *plugin_name(void);\n"));
+    assert!(header.contains("bool plugin_norm(const Point *point, double *out, const void **);\n"));
+    assert!(header.contains("void plugin_free(uint8_t *_data, uintptr_t _len);\n"));
+    assert!(header.contains("uint32_t plugin_version(void);\n"));
+    assert!(header.contains("void plugin_std(void);\n"));
+    assert!(header.contains("void plugin_dashed(void);\n"));
+    assert!(!header.contains("plugin_underscored"));
+    assert!(!header.contains("plugin_ffi"));
+    assert!(!header.contains("plugin_mangled"));
+    // Temporary files are renamed over the ones they replace.
+    assert!(!fs::read_dir(&out_dir).unwrap().any(|entry| entry
+        .unwrap()
+        .path()
+        .extension()
+        .unwrap_or_default()
+        == "tmp"));
+
+    fs::remove_dir_all(&out_dir).unwrap();
+}
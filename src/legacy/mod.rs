@@ -1,3 +1,8 @@
+//! The attributes from before 0.2.0, which apply the same qualifiers as `#[qualifiers]` to a
+//! single kind of item. They are deprecated in `lib.rs`, so that every use warns.
+
+use qualifier_attr_core::Qualifiers;
+
 mod fn_qualifiers;
 mod mod_qualifiers;
 mod named_field_qualifiers;
@@ -7,3 +12,17 @@ pub(super) use fn_qualifiers::fn_qualifiers;
 pub(super) use mod_qualifiers::mod_qualifiers;
 pub(super) use named_field_qualifiers::named_field_qualifiers;
 pub(super) use struct_qualifiers::struct_qualifiers;
+
+/// Parses the qualifiers of a legacy attribute, which are those of `#[qualifiers]` without the
+/// modes that emit other items and without conditions.
+fn parse_qualifiers(meta: pm::TokenStream) -> syn::Result<Qualifiers> {
+    let qualifiers = syn::parse::<Qualifiers>(meta)?;
+    qualifiers.check_item_only()?;
+    if let Some(condition) = qualifiers.conditions.first() {
+        return Err(syn::Error::new(
+            condition.qualifier.span(),
+            "conditional qualifiers are only supported by `#[qualifiers]`",
+        ));
+    }
+    Ok(qualifiers)
+}
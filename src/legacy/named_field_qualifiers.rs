@@ -1,26 +1,23 @@
 use proc_macro as pm;
+use qualifier_attr_core::QualifiersLens;
 use quote::ToTokens;
-use syn::parse::{Parse, ParseStream};
+use syn::parse::Parser;
 
-struct NamedFieldQualifier(syn::Visibility);
-
-impl Parse for NamedFieldQualifier {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        Ok(NamedFieldQualifier(input.parse()?))
+pub(crate) fn named_field_qualifiers(
+    meta: pm::TokenStream,
+    item: pm::TokenStream,
+) -> pm::TokenStream {
+    fn inner(meta: pm::TokenStream, item: pm::TokenStream) -> syn::Result<pm::TokenStream> {
+        let qualifiers = super::parse_qualifiers(meta)?;
+        let mut field = syn::Field::parse_named.parse(item)?;
+        QualifiersLens::new()
+            .visibility(&mut field.vis)
+            .apply(qualifiers)?;
+        Ok(field.into_token_stream().into())
     }
-}
-
-struct NamedField(syn::Field);
 
-impl Parse for NamedField {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        Ok(NamedField(syn::Field::parse_named(input)?))
+    match inner(meta, item.clone()) {
+        Ok(output) => output,
+        Err(error) => crate::emit_error(error, item.into()),
     }
 }
-
-pub(crate) fn named_field_qualifiers(meta: pm::TokenStream, item: pm::TokenStream) -> pm::TokenStream {
-    let NamedFieldQualifier(vis) = syn::parse_macro_input!(meta as NamedFieldQualifier);
-    let NamedField ( mut item ) = syn::parse_macro_input!(item as NamedField);
-    item.vis = vis;
-    item.to_token_stream().into()
-}
@@ -1,18 +1,19 @@
-use syn::parse::{Parse, ParseStream};
 use proc_macro as pm;
+use qualifier_attr_core::QualifiersLens;
 use quote::ToTokens;
 
-struct StructQualifier(syn::Visibility);
-
-impl Parse for StructQualifier {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        Ok(StructQualifier(input.parse()?))
+pub(crate) fn struct_qualifiers(meta: pm::TokenStream, item: pm::TokenStream) -> pm::TokenStream {
+    fn inner(meta: pm::TokenStream, item: pm::TokenStream) -> syn::Result<pm::TokenStream> {
+        let qualifiers = super::parse_qualifiers(meta)?;
+        let mut item = syn::parse::<syn::ItemStruct>(item)?;
+        QualifiersLens::new()
+            .visibility(&mut item.vis)
+            .apply(qualifiers)?;
+        Ok(item.into_token_stream().into())
     }
-}
 
-pub(crate) fn struct_qualifiers(meta: pm::TokenStream, item: pm::TokenStream) -> pm::TokenStream {
-    let meta = syn::parse_macro_input!(meta as StructQualifier);
-    let mut item = syn::parse_macro_input!(item as syn::ItemStruct);
-    item.vis = meta.0;
-    item.to_token_stream().into()
+    match inner(meta, item.clone()) {
+        Ok(output) => output,
+        Err(error) => crate::emit_error(error, item.into()),
+    }
 }
@@ -0,0 +1,23 @@
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+// ABIs are checked against the ones rustc knows, which is where typos like `extern "c"` or
+// `extern "sytem"` are reported.
+#[qualifiers(extern "C-unwind")]
+fn c_unwind() -> u32 {
+    42
+}
+
+#[qualifiers(extern "system")]
+fn system() -> u32 {
+    42
+}
+
+#[test]
+fn test_abi() {
+    let _: extern "C-unwind" fn() -> u32 = c_unwind;
+    let _: extern "system" fn() -> u32 = system;
+    assert_eq!(c_unwind() + system(), 84);
+}
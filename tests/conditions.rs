@@ -0,0 +1,67 @@
+#![cfg(feature = "full")]
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+// Qualifiers can depend on `cfg` predicates...
+#[qualifiers(const if all())]
+fn answer() -> u32 {
+    42
+}
+
+const ANSWER: u32 = answer();
+
+// ...and have alternatives for when the predicate doesn't hold.
+#[qualifiers(const, extern "C" if all() else extern "Rust")]
+fn forty_two() -> u32 {
+    42
+}
+
+const FORTY_TWO: u32 = forty_two();
+const FORTY_TWO_PTR: extern "C" fn() -> u32 = forty_two;
+
+mod foo {
+    #[qualifiers(pub if test else pub(crate))]
+    pub(super) fn foo() -> u32 {
+        42
+    }
+}
+
+struct Bar {
+    a: u32,
+}
+
+// Conditions work for fields and members as well.
+#[field_qualifiers(a(pub if all()))]
+struct Baz {
+    a: u32,
+}
+
+#[impl_qualifiers(new(const if any() else const))]
+impl Bar {
+    fn new() -> Self {
+        Bar { a: 42 }
+    }
+}
+
+#[qualify_members]
+impl Baz {
+    #[qualify(const if all())]
+    fn new() -> Self {
+        Baz { a: 42 }
+    }
+}
+
+const BAR: Bar = Bar::new();
+const BAZ: Baz = Baz::new();
+
+#[test]
+fn test_conditions() {
+    assert_eq!(ANSWER, 42);
+    assert_eq!(FORTY_TWO, 42);
+    assert_eq!(FORTY_TWO_PTR(), 42);
+    assert_eq!(foo::foo(), 42);
+    assert_eq!(BAR.a, 42);
+    assert_eq!(BAZ.a, 42);
+}
@@ -0,0 +1,73 @@
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+// Bodies made `const` are checked for constructs such as `for` loops, `?`,
+// and calls through traits, which can't be evaluated at compile time.
+#[qualifiers(const)]
+fn sum(values: &[u32]) -> u32 {
+    let mut sum = 0;
+    let mut i = 0;
+    while i < values.len() {
+        sum += values[i];
+        i += 1;
+    }
+    sum
+}
+
+const SUM: u32 = sum(&[20, 22]);
+
+struct Meters(u32);
+
+impl Meters {
+    const fn eq(&self, other: &Meters) -> bool {
+        self.0 == other.0
+    }
+
+    const fn map(self, _f: fn(u32) -> u32) -> u32 {
+        self.0
+    }
+}
+
+// Methods, closures, and trait objects may all be `const`, so the check leaves
+// them to the compiler.
+#[qualifiers(const)]
+fn same(a: &Meters, b: &Meters) -> bool {
+    a.eq(b)
+}
+
+const SAME: bool = same(&Meters(42), &Meters(42));
+
+#[qualifiers(const)]
+fn with_closure() -> u32 {
+    Meters(42).map(|x| x * 2)
+}
+
+const WITH_CLOSURE: u32 = with_closure();
+
+#[qualifiers(const)]
+fn as_debug(value: &u32) -> &dyn std::fmt::Debug {
+    value
+}
+
+#[qualifiers(const)]
+fn empty() -> Vec<u32> {
+    vec![]
+}
+
+// The check can still be skipped where it is wrong.
+#[qualifiers(const(unchecked))]
+fn also_same(a: &Meters, b: &Meters) -> bool {
+    a.eq(b)
+}
+
+#[test]
+fn test_const_check() {
+    assert_eq!(SUM, 42);
+    assert!(SAME);
+    assert_eq!(WITH_CLOSURE, 42);
+    assert_eq!(format!("{:?}", as_debug(&42)), "42");
+    assert!(empty().is_empty());
+    assert!(also_same(&Meters(42), &Meters(42)));
+}
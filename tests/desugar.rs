@@ -0,0 +1,93 @@
+#![cfg(feature = "full")]
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+// `async(send)` desugars trait methods into ones returning `impl Future + Send`...
+#[trait_qualifiers(answer(async(send)), offset(async(send)), default_answer(async(send)))]
+trait Answer {
+    fn answer(&self) -> u32;
+
+    fn offset(&self, x: &u32) -> u32;
+
+    fn default_answer(&self) -> u32 {
+        42
+    }
+}
+
+struct Foo(u32);
+
+// ...and requires the futures of implementations to be `Send`.
+#[impl_qualifiers(answer(async(send)), offset(async(send)))]
+impl Answer for Foo {
+    fn answer(&self) -> u32 {
+        self.0
+    }
+
+    fn offset(&self, x: &u32) -> u32 {
+        self.0 + x
+    }
+}
+
+// `async(desugar)` does the same without requiring `Send`.
+#[qualifiers(async(desugar))]
+fn forty_two() {}
+
+#[qualifiers(async(desugar))]
+async fn answer(x: u32) -> u32 {
+    x
+}
+
+// Outside traits, the future captures the lifetimes of the parameters like `async fn` does.
+#[qualifiers(async(desugar))]
+async fn first<T: Copy>(values: &[T], _name: &str) -> T {
+    values[0]
+}
+
+struct Bar(u32);
+
+#[qualifiers(each(fn), async(desugar))]
+impl Bar {
+    fn add(&self, x: &u32) -> u32 {
+        self.0 + x
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+fn assert_send<T: Send>(value: T) -> T {
+    value
+}
+
+#[test]
+fn test_desugar() {
+    let foo = Foo(42);
+    assert_eq!(block_on(assert_send(foo.answer())), 42);
+    assert_eq!(block_on(assert_send(foo.default_answer())), 42);
+    assert_eq!(block_on(assert_send(foo.offset(&0))), 42);
+    let _: () = block_on(forty_two());
+    assert_eq!(block_on(answer(42)), 42);
+    assert_eq!(block_on(first(&[42], "first")), 42);
+    assert_eq!(block_on(Bar(21).add(&21)), 42);
+}
@@ -0,0 +1,100 @@
+#![cfg(feature = "full")]
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+// `dual` emits the function as written along with a renamed twin that has
+// the qualifier toggled, stripping `.await` when `async` is removed...
+#[qualifiers(pub, dual(async, suffix = "_blocking"))]
+async fn answer() -> u32 {
+    async { 42 }.await
+}
+
+// ...or adding `async`, `const`, or `unsafe`.
+#[qualifiers(dual(async, suffix = "_async"))]
+fn forty_two() -> u32 {
+    42
+}
+
+#[qualifiers(dual(const, prefix = "runtime_"))]
+const fn double(x: u32) -> u32 {
+    x * 2
+}
+
+const DOUBLE: u32 = double(21);
+
+struct Foo;
+
+// With `each`, every selected function of a module or implementation gets a twin.
+#[qualifiers(each(fn), dual(unsafe, suffix = "_unchecked"))]
+impl Foo {
+    fn get(&self, values: &[u32]) -> u32 {
+        values[0]
+    }
+}
+
+// Twins call the twins of the other functions that get one, so the blocking
+// `fetch` calls the blocking `helper`...
+#[qualifiers(each(fn), dual(async, suffix = "_blocking"))]
+mod client {
+    pub async fn helper() -> u32 {
+        async { 21 }.await
+    }
+
+    pub async fn fetch() -> u32 {
+        helper().await * 2
+    }
+}
+
+// ...and the `async` twin of `total` awaits the `async` twin of `part`.
+struct Counter(u32);
+
+#[qualifiers(each(fn), dual(async, suffix = "_async"))]
+impl Counter {
+    fn part(&self) -> u32 {
+        self.0
+    }
+
+    fn total(&self) -> u32 {
+        self.part() + Self::part(self)
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn test_dual() {
+    assert_eq!(block_on(answer()), 42);
+    assert_eq!(answer_blocking(), 42);
+    assert_eq!(forty_two(), 42);
+    assert_eq!(block_on(forty_two_async()), 42);
+    assert_eq!(DOUBLE, 42);
+    assert_eq!(runtime_double(21), 42);
+    assert_eq!(Foo.get(&[42]), 42);
+    assert_eq!(unsafe { Foo.get_unchecked(&[42]) }, 42);
+    assert_eq!(block_on(client::fetch()), 42);
+    assert_eq!(client::fetch_blocking(), 42);
+    assert_eq!(Counter(21).total(), 42);
+    assert_eq!(block_on(Counter(21).total_async()), 42);
+}
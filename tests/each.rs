@@ -0,0 +1,53 @@
+#![cfg(feature = "full")]
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+// Qualifiers can be applied to every member of a module...
+#[qualifiers(each, pub)]
+mod foo {
+    fn foo() -> u32 {
+        42
+    }
+
+    struct Foo;
+
+    const FOO: u32 = 42;
+}
+
+// ...or only to some of them, by kind or by name.
+#[qualifiers(each(fn, "get_*"), pub, const)]
+mod bar {
+    fn get_bar() -> u32 {
+        42
+    }
+
+    pub fn bar() -> u32 {
+        42
+    }
+}
+
+const BAR: u32 = bar::get_bar();
+
+struct Baz;
+
+// Members that don't support a qualifier can be skipped.
+#[qualifiers(each(lenient), pub(crate), const)]
+impl Baz {
+    fn baz() -> u32 {
+        42
+    }
+
+    const BAZ: u32 = 42;
+}
+
+const BAZ: u32 = Baz::baz();
+
+#[test]
+fn qualified_members() {
+    let _ = foo::Foo;
+    assert_eq!(foo::foo(), foo::FOO);
+    assert_eq!(bar::bar(), BAR);
+    assert_eq!(Baz::BAZ, BAZ);
+}
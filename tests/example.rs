@@ -1,4 +1,5 @@
-#![allow(dead_code)]
+#![cfg(feature = "full")]
+#![allow(dead_code, unexpected_cfgs)]
 
 #[macro_use]
 extern crate qualifier_attr;
@@ -54,3 +55,17 @@ struct Point2 {
 
 #[field_qualifiers(_0(pub), _1(pub), _2(pub))]
 struct Point3(i32, i32, i32);
+
+// The same goes for the members of implementations...
+#[impl_qualifiers(new(pub, const))]
+impl Point2 {
+    fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+}
+
+// ...and traits.
+#[trait_qualifiers(quux_the_thing(unsafe))]
+trait UnsafeQuux {
+    fn quux_the_thing();
+}
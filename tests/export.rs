@@ -0,0 +1,72 @@
+#![cfg(feature = "full")]
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+// `export` applies `extern "C"` and exports the function under its own name...
+#[qualifiers(pub, export)]
+fn export_answer() -> u32 {
+    42
+}
+
+// ...or another one.
+#[qualifiers(export(name = "export_renamed"))]
+fn renamed() -> u32 {
+    42
+}
+
+// Other ABIs are kept.
+#[qualifiers(extern "system", export(prefix = "export_"))]
+fn system() -> u32 {
+    42
+}
+
+// `unsafe` emits `#[unsafe(no_mangle)]`, as edition 2024 writes it.
+#[qualifiers(export(unsafe))]
+fn export_unsafe() -> u32 {
+    42
+}
+
+// With `each`, every function is exported, so a prefix is usually given.
+#[qualifiers(each(fn), export(prefix = "export_each_"))]
+mod functions {
+    pub fn first() -> u32 {
+        1
+    }
+
+    pub fn second() -> u32 {
+        2
+    }
+
+    pub const THIRD: u32 = 3;
+}
+
+extern "C" {
+    #[link_name = "export_answer"]
+    fn exported_answer() -> u32;
+    fn export_renamed() -> u32;
+    #[link_name = "export_unsafe"]
+    fn exported_unsafe() -> u32;
+    fn export_each_first() -> u32;
+    fn export_each_second() -> u32;
+}
+
+extern "system" {
+    fn export_system() -> u32;
+}
+
+#[test]
+fn test_export() {
+    let _: extern "C" fn() -> u32 = renamed;
+    let _: extern "system" fn() -> u32 = system;
+    let _: extern "C" fn() -> u32 = functions::first;
+    unsafe {
+        assert_eq!(exported_answer(), 42);
+        assert_eq!(export_renamed(), 42);
+        assert_eq!(exported_unsafe(), 42);
+        assert_eq!(export_system(), 42);
+        assert_eq!(export_each_first(), 1);
+        assert_eq!(export_each_second(), 2);
+    }
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "full")]
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+#[repr(C)]
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+// The Rust functions are left alone...
+#[qualifiers(pub, extern_shim("C", name = "shim_point_x", on_panic = -1))]
+fn point_x(point: Option<&Point>, double: bool) -> i32 {
+    let x = point.expect("no point").x;
+    if double {
+        x * 2
+    } else {
+        x
+    }
+}
+
+#[qualifiers(extern_shim("C", unsafe))]
+fn shim_is_even(n: u32) -> bool {
+    n % 2 == 0
+}
+
+// Without `std`, the shim can't catch panics, so it calls the function directly.
+#[qualifiers(extern_shim("C", name = "shim_double", panic = "abort"))]
+fn double(n: i32) -> i32 {
+    n * 2
+}
+
+#[qualifiers(extern_shim("C", name = "shim_first"))]
+fn first(points: Option<&mut Point>) -> Option<&mut i32> {
+    points.map(|point| &mut point.x)
+}
+
+// ...while the shims are exported under the given names.
+extern "C" {
+    fn shim_point_x(point: *const Point, double: bool) -> i32;
+    #[link_name = "shim_is_even"]
+    fn shim_is_even_c(n: u32) -> bool;
+    fn shim_double(n: i32) -> i32;
+    fn shim_first(point: *mut Point) -> *mut i32;
+}
+
+#[test]
+fn test_extern_shim() {
+    let mut point = Point { x: 21, y: 0 };
+    assert_eq!(point_x(Some(&point), true), 42);
+    assert!(shim_is_even(42));
+
+    unsafe {
+        assert_eq!(shim_point_x(&point, true), 42);
+        assert_eq!(shim_point_x(&point, false), 21);
+        // Panics are caught.
+        assert_eq!(shim_point_x(std::ptr::null(), false), -1);
+        assert!(shim_is_even_c(42));
+        assert_eq!(shim_double(21), 42);
+        assert_eq!(*shim_first(&mut point), 21);
+        assert!(shim_first(std::ptr::null_mut()).is_null());
+    }
+}
@@ -0,0 +1,48 @@
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+use std::os::raw::c_char;
+
+#[repr(C)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+// Functions given a foreign ABI are checked for types that foreign code can't use,
+// such as `String`, `&str`, `Vec<T>`, slices, tuples, trait objects, and generics.
+#[qualifiers(extern "C")]
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[qualifiers(extern "C")]
+fn norm(point: &Point, name: *const c_char) -> Option<&i32> {
+    let _ = name;
+    Some(&point.x)
+}
+
+// The check is syntactic, so it can be skipped where it is too strict, e.g. for generic
+// functions that are only used as function pointers once instantiated.
+#[qualifiers(extern "C"(unchecked))]
+fn size_of_pointee<T>(_: *const T) -> usize {
+    std::mem::size_of::<T>()
+}
+
+// Rust ABIs aren't checked at all.
+#[qualifiers(extern "Rust")]
+fn greet(name: &str) -> String {
+    format!("Hello, {}!", name)
+}
+
+#[test]
+fn test_ffi_check() {
+    let add_ptr: extern "C" fn(i32, i32) -> i32 = add;
+    assert_eq!(add_ptr(40, 2), 42);
+    assert_eq!(norm(&Point { x: 42, y: 0 }, std::ptr::null()), Some(&42));
+    let size_ptr: extern "C" fn(*const u64) -> usize = size_of_pointee::<u64>;
+    assert_eq!(size_ptr(std::ptr::null()), 8);
+    assert_eq!(greet("world"), "Hello, world!");
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "full")]
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+mod foo {
+    // Every field can be selected at once...
+    #[field_qualifiers(*(pub))]
+    pub struct Everything {
+        x: i32,
+        y: i32,
+    }
+
+    // ...or every field except some.
+    #[field_qualifiers(* except secret(pub))]
+    pub struct AllButSecret {
+        name: String,
+        secret: String,
+    }
+
+    impl AllButSecret {
+        pub fn new(name: String) -> Self {
+            Self {
+                name,
+                secret: String::new(),
+            }
+        }
+    }
+
+    // Tuple fields can be selected by index or by range...
+    #[field_qualifiers(0(pub), 1..=2(pub(crate)))]
+    pub struct Triple(i32, i32, i32);
+
+    // ...and fields can be selected by type. Selectors may overlap as long as
+    // they agree on the visibility.
+    #[field_qualifiers(: Vec<(u8, u8)> (pub), r#ty(pub), *(pub))]
+    pub struct Typed {
+        pairs: Vec<(u8, u8)>,
+        other: Vec<(u8, u8)>,
+        ty: u8,
+    }
+
+    // Qualifiers can also be placed on the fields themselves,
+    // conditionally or not.
+    #[field_qualifiers]
+    #[derive(Default)]
+    pub struct Marked {
+        #[qualify(pub)]
+        x: i32,
+        #[cfg_attr(test, qualify(pub))]
+        y: i32,
+        #[cfg_attr(any(), qualify(pub))]
+        z: i32,
+    }
+
+    #[field_qualifiers]
+    pub struct MarkedTuple(#[cfg_attr(all(test, not(any())), qualify(pub))] pub(crate) i32);
+}
+
+#[test]
+fn selected_fields() {
+    let everything = foo::Everything { x: 1, y: 2 };
+    assert_eq!(everything.x + everything.y, 3);
+
+    let all_but_secret = foo::AllButSecret::new(String::from("name"));
+    assert_eq!(all_but_secret.name, "name");
+
+    let triple = foo::Triple(1, 2, 3);
+    assert_eq!(triple.0 + triple.1 + triple.2, 6);
+
+    let typed = foo::Typed {
+        pairs: Vec::new(),
+        other: Vec::new(),
+        ty: 0,
+    };
+    assert!(typed.pairs.is_empty() && typed.other.is_empty());
+    assert_eq!(typed.ty, 0);
+
+    let marked = foo::Marked::default();
+    assert_eq!(marked.x + marked.y, 0);
+
+    let marked_tuple = foo::MarkedTuple(1);
+    assert_eq!(marked_tuple.0, 1);
+}
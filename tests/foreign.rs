@@ -0,0 +1,23 @@
+#[macro_use]
+extern crate qualifier_attr;
+
+// Foreign functions and statics can be marked `safe`
+// inside of `unsafe extern` blocks...
+#[qualifiers(unsafe)]
+extern "C" {
+    #[qualifiers(safe)]
+    fn abs(x: i32) -> i32;
+}
+
+// ...and the ABI of the block itself can be changed.
+#[qualifiers(extern "C")]
+unsafe extern "system" {
+    #[qualifiers(safe)]
+    unsafe fn labs(x: i64) -> i64;
+}
+
+#[test]
+fn safe_foreign_fns() {
+    assert_eq!(abs(-3), 3);
+    assert_eq!(labs(-4), 4);
+}
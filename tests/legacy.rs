@@ -0,0 +1,47 @@
+#![cfg(feature = "legacy_attrs")]
+#![allow(dead_code, deprecated)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+// The legacy attributes apply qualifiers like `#[qualifiers]`, so qualifiers that aren't listed
+// are kept, and `exact` removes them like the legacy attributes used to.
+mod inner {
+    #[fn_qualifiers(pub, const)]
+    unsafe fn answer() -> u32 {
+        42
+    }
+
+    #[fn_qualifiers(exact, const)]
+    pub unsafe extern "C" fn plain() -> u32 {
+        7
+    }
+
+    #[struct_qualifiers(pub)]
+    struct Unit;
+
+    // An empty list used to make the struct private, but now leaves it alone.
+    #[struct_qualifiers()]
+    pub struct StillPublic;
+
+    #[mod_qualifiers(pub)]
+    mod nested {
+        pub const NESTED: u32 = 1;
+    }
+}
+
+#[struct_qualifiers(exact)]
+pub struct Private;
+
+const ANSWER: u32 = unsafe { inner::answer() };
+const PLAIN: u32 = inner::plain();
+
+#[test]
+fn legacy_attributes() {
+    assert_eq!(ANSWER, 42);
+    assert_eq!(PLAIN, 7);
+    let _ = inner::Unit;
+    let _ = inner::StillPublic;
+    assert_eq!(inner::nested::NESTED, 1);
+    let _ = Private;
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "full")]
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+struct Foo;
+
+// Members of implementations can be qualified by name...
+#[impl_qualifiers(new(pub, const), answer(const))]
+impl Foo {
+    fn new() -> Self {
+        Foo
+    }
+
+    fn answer(&self) -> u32 {
+        42
+    }
+}
+
+const FOO: Foo = Foo::new();
+const ANSWER: u32 = FOO.answer();
+
+// ...and so can members of traits.
+#[trait_qualifiers(quux(unsafe))]
+trait Quux {
+    fn quux(&self) -> u32;
+}
+
+impl Quux for Foo {
+    unsafe fn quux(&self) -> u32 {
+        42
+    }
+}
+
+struct Bar;
+
+// Members can also be qualified with helper attributes,
+// conditionally or not.
+#[qualify_members]
+impl Bar {
+    #[qualify(const)]
+    fn new() -> Self {
+        Bar
+    }
+
+    #[cfg_attr(test, qualify(const))]
+    fn answer(&self) -> u32 {
+        42
+    }
+
+    #[cfg_attr(any(), qualify(async))]
+    fn sync_answer(&self) -> u32 {
+        42
+    }
+}
+
+const BAR_ANSWER: u32 = Bar::new().answer();
+
+#[qualify_members]
+unsafe extern "C" {
+    #[qualify(safe)]
+    fn abs(x: i32) -> i32;
+}
+
+#[test]
+fn qualified_members() {
+    assert_eq!(Bar.sync_answer(), BAR_ANSWER);
+    assert_eq!(abs(-42), BAR_ANSWER as i32);
+    assert_eq!(unsafe { Foo.quux() }, ANSWER);
+}
@@ -0,0 +1,37 @@
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+// Qualifiers can be removed as well as added.
+#[qualifiers(!async)]
+async fn sync_fn() -> u32 {
+    42
+}
+
+#[qualifiers(!unsafe, const)]
+unsafe fn safe_const_fn() -> u32 {
+    42
+}
+
+const SAFE_CONST_RES: u32 = safe_const_fn();
+
+mod foo {
+    #[qualifiers(priv)]
+    pub struct Foo;
+}
+
+// In exact mode, everything that isn't listed is removed.
+#[qualifiers(exact, const)]
+pub unsafe extern "C" fn exact_fn() -> u32 {
+    42
+}
+
+const EXACT_RES: u32 = exact_fn();
+
+#[test]
+fn removed_qualifiers() {
+    let exact_fn: fn() -> u32 = exact_fn;
+    assert_eq!(exact_fn(), 42);
+    assert_eq!(sync_fn(), 42);
+}
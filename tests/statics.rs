@@ -0,0 +1,20 @@
+#[macro_use]
+extern crate qualifier_attr;
+
+// Statics can be made mutable...
+#[qualifiers(mut)]
+static COUNTER: u32 = 0;
+
+// ...or immutable.
+#[qualifiers(!mut)]
+static mut FROZEN: u32 = 42;
+
+#[test]
+fn static_mutability() {
+    unsafe {
+        COUNTER += 1;
+        let counter = COUNTER;
+        assert_eq!(counter, 1);
+    }
+    assert_eq!(FROZEN, 42);
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "full")]
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+// Removing `async` with `strip_await` removes `.await` from the body as well,
+// so the same code can be compiled synchronously...
+#[qualifiers(!async(strip_await))]
+async fn answer(x: u32) -> u32 {
+    x
+}
+
+#[qualifiers(!async(strip_await) if all())]
+async fn forty_two() -> Result<u32, ()> {
+    let x = answer(40).await;
+    // ...including async blocks that are awaited right away...
+    let y = async {
+        let one: Result<u32, ()> = Ok(1);
+        Ok::<u32, ()>(one? + 1)
+    }
+    .await?;
+    // ...and async closures.
+    let add = |a: u32, b: u32| async move { a + b };
+    Ok(add(x, y).await)
+}
+
+struct Foo;
+
+#[qualifiers(each(fn), !async(strip_await))]
+impl Foo {
+    async fn answer(&self) -> u32 {
+        answer(42).await
+    }
+}
+
+#[test]
+fn test_strip_await() {
+    assert_eq!(answer(42), 42);
+    assert_eq!(forty_two(), Ok(42));
+    assert_eq!(Foo.answer(), 42);
+}
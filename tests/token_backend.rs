@@ -0,0 +1,48 @@
+#![allow(dead_code)]
+
+#[macro_use]
+extern crate qualifier_attr;
+
+// With the `token_backend` feature, qualifiers that only change the header of an item are applied
+// to its tokens, and the body is left unparsed. The results are the same either way.
+mod inner {
+    #[qualifiers(pub(crate), const(unchecked))]
+    fn answer() -> u32 {
+        42
+    }
+
+    #[qualifiers(pub, !mut)]
+    static mut ANSWER: u32 = 42;
+
+    #[qualifiers(exact, pub)]
+    pub(crate) unsafe extern "C" fn plain() -> u32 {
+        7
+    }
+
+    #[qualifiers(pub, async)]
+    fn later() -> u32 {
+        1
+    }
+}
+
+/// # Safety
+///
+/// Implementations have nothing to uphold.
+#[qualifiers(unsafe)]
+trait Marker {}
+
+#[qualifiers(unsafe)]
+impl Marker for u8 {}
+
+const ANSWER: u32 = inner::answer();
+
+fn returns_future<F: std::future::Future<Output = u32>>(_: F) {}
+
+#[test]
+fn header_only_qualifiers() {
+    assert_eq!(ANSWER, 42);
+    assert_eq!(inner::ANSWER, 42);
+    let plain: fn() -> u32 = inner::plain;
+    assert_eq!(plain(), 7);
+    returns_future(inner::later());
+}